@@ -0,0 +1,212 @@
+//! Unsigned integers of arbitrary size for counts which outgrow `u64`.
+
+use std::cmp::Ordering;
+use std::fmt;
+use std::ops::{Add, Mul, Sub};
+use std::str::FromStr;
+
+/// Numbers things can be counted in, e.g. `u64` or, without overflow, `BigUint`.
+pub trait Count: Clone + Ord + From<u64> + Add<Output = Self> + Mul<Output = Self> + Sub<Output = Self> {
+    /// Nearest floating point number, e.g. to compute growth rates.
+    fn to_f64(&self) -> f64;
+}
+
+impl Count for u64 {
+    fn to_f64(&self) -> f64 {
+        *self as f64
+    }
+}
+
+impl Count for BigUint {
+    fn to_f64(&self) -> f64 {
+        self.digits.iter().rev().fold(0.0, |value, &digit| value * 4294967296.0 + digit as f64)
+    }
+}
+
+/// Unsigned integer of arbitrary size, stored as base 2^32 digits with the
+/// least significant first and no leading zeros.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BigUint {
+    digits: Vec<u32>,
+}
+
+impl BigUint {
+    pub fn is_zero(&self) -> bool {
+        self.digits.is_empty()
+    }
+
+    fn trim(mut self) -> BigUint {
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        self
+    }
+
+    /// Divide in place by a small divisor and return the remainder.
+    fn div_rem_small(&mut self, divisor: u32) -> u32 {
+        let mut remainder: u64 = 0;
+        for digit in self.digits.iter_mut().rev() {
+            let current = (remainder << 32) | *digit as u64;
+            *digit = (current / divisor as u64) as u32;
+            remainder = current % divisor as u64;
+        }
+        while self.digits.last() == Some(&0) {
+            self.digits.pop();
+        }
+        remainder as u32
+    }
+}
+
+impl From<u64> for BigUint {
+    fn from(value: u64) -> BigUint {
+        BigUint { digits: vec![value as u32, (value >> 32) as u32] }.trim()
+    }
+}
+
+/// Error for strings which are not a non-empty sequence of decimal digits.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ParseBigUintError;
+
+impl FromStr for BigUint {
+    type Err = ParseBigUintError;
+
+    fn from_str(s: &str) -> Result<BigUint, ParseBigUintError> {
+        if s.is_empty() || !s.bytes().all(|b| b.is_ascii_digit()) {
+            return Err(ParseBigUintError);
+        }
+        Ok(s.bytes().fold(BigUint::from(0), |value, b| {
+            value * BigUint::from(10) + BigUint::from((b - b'0') as u64)
+        }))
+    }
+}
+
+impl Add for BigUint {
+    type Output = BigUint;
+
+    fn add(self, other: BigUint) -> BigUint {
+        let n = self.digits.len().max(other.digits.len());
+        let mut digits = Vec::with_capacity(n + 1);
+        let mut carry: u64 = 0;
+        for i in 0..n {
+            let sum = *self.digits.get(i).unwrap_or(&0) as u64 + *other.digits.get(i).unwrap_or(&0) as u64 + carry;
+            digits.push(sum as u32);
+            carry = sum >> 32;
+        }
+        digits.push(carry as u32);
+        BigUint { digits }.trim()
+    }
+}
+
+impl Mul for BigUint {
+    type Output = BigUint;
+
+    fn mul(self, other: BigUint) -> BigUint {
+        if self.is_zero() || other.is_zero() {
+            return BigUint { digits: Vec::new() };
+        }
+        let mut digits = vec![0u32; self.digits.len() + other.digits.len()];
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut carry: u64 = 0;
+            for (j, &b) in other.digits.iter().enumerate() {
+                let current = digits[i + j] as u64 + a as u64 * b as u64 + carry;
+                digits[i + j] = current as u32;
+                carry = current >> 32;
+            }
+            digits[i + other.digits.len()] = carry as u32;
+        }
+        BigUint { digits }.trim()
+    }
+}
+
+impl Sub for BigUint {
+    type Output = BigUint;
+
+    /// Panics if `other` is larger than `self`.
+    fn sub(self, other: BigUint) -> BigUint {
+        assert!(self >= other, "Subtraction would underflow");
+        let mut digits = Vec::with_capacity(self.digits.len());
+        let mut borrow: i64 = 0;
+        for (i, &a) in self.digits.iter().enumerate() {
+            let mut current = a as i64 - *other.digits.get(i).unwrap_or(&0) as i64 - borrow;
+            borrow = 0;
+            if current < 0 {
+                current += 1 << 32;
+                borrow = 1;
+            }
+            digits.push(current as u32);
+        }
+        BigUint { digits }.trim()
+    }
+}
+
+impl Ord for BigUint {
+    fn cmp(&self, other: &BigUint) -> Ordering {
+        self.digits
+            .len()
+            .cmp(&other.digits.len())
+            .then_with(|| self.digits.iter().rev().cmp(other.digits.iter().rev()))
+    }
+}
+
+impl PartialOrd for BigUint {
+    fn partial_cmp(&self, other: &BigUint) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl fmt::Display for BigUint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_zero() {
+            return f.write_str("0");
+        }
+        // Split off nine decimal digits at a time.
+        let mut value = self.clone();
+        let mut chunks = Vec::new();
+        while !value.is_zero() {
+            chunks.push(value.div_rem_small(1_000_000_000));
+        }
+        let mut s = chunks.pop().unwrap().to_string();
+        for chunk in chunks.iter().rev() {
+            s += &format!("{:09}", chunk);
+        }
+        f.write_str(&s)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test01() {
+        let a = BigUint::from(u64::MAX);
+        let b = BigUint::from(1);
+        assert_eq!((a.clone() + b.clone()).to_string(), "18446744073709551616");
+        assert_eq!((a.clone() * a.clone()).to_string(), "340282366920938463426481119284349108225");
+        assert_eq!(((a.clone() + b.clone()) - b.clone()), a);
+        assert_eq!(BigUint::from(0).to_string(), "0");
+        assert_eq!((BigUint::from(1_000_000_000) * BigUint::from(1_000_000_000)).to_string(), "1000000000000000000");
+    }
+
+    #[test]
+    fn test02() {
+        // 2^200 and 3^100
+        let two = (0..200).fold(BigUint::from(1), |p, _| p * BigUint::from(2));
+        let three = (0..100).fold(BigUint::from(1), |p, _| p * BigUint::from(3));
+        assert_eq!(two.to_string(), "1606938044258990275541962092341162602522202993782792835301376");
+        assert_eq!(three.to_string(), "515377520732011331036461129765621272702107522001");
+        assert!(three < two);
+        assert!(BigUint::from(5) > BigUint::from(4));
+        assert_eq!(two.clone() - two, BigUint::from(0));
+    }
+
+    #[test]
+    fn test03() {
+        let value: BigUint = "340282366920938463426481119284349108225".parse().unwrap();
+        assert_eq!(value, BigUint::from(u64::MAX) * BigUint::from(u64::MAX));
+        assert_eq!("007".parse::<BigUint>(), Ok(BigUint::from(7)));
+        assert_eq!("".parse::<BigUint>(), Err(ParseBigUintError));
+        assert_eq!("12a".parse::<BigUint>(), Err(ParseBigUintError));
+        assert!((value.to_f64() / 2f64.powi(128) - 1.0).abs() < 1e-12);
+    }
+}
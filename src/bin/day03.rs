@@ -22,11 +22,11 @@ fn read_input(filename: &str) -> (Vec<u32>, usize) {
     (input, bits)
 }
 
-fn count_set_bit(values: &Vec<u32>, bit: usize) -> usize {
+fn count_set_bit(values: &[u32], bit: usize) -> usize {
     values.iter().filter(|v| (*v >> bit) & 1 == 1).count()
 }
 
-fn part1(input: &Vec<u32>, bits: usize) -> usize {
+fn part1(input: &[u32], bits: usize) -> usize {
     let mut gamma_rate = 0;
     let mut epsilon_rate = 0;
 
@@ -42,52 +42,267 @@ fn part1(input: &Vec<u32>, bits: usize) -> usize {
     gamma_rate * epsilon_rate
 }
 
-fn part2(input: &Vec<u32>, bits: usize) -> u32 {
-    let mut values = input.clone();
-    let mut bit = bits - 1;
-    while values.len() > 1 {
-        let bc = count_set_bit(&values, bit);
-        let most_common_bit = if bc * 2 >= values.len() {1} else {0};
-        // println!("{}: {} {}, {:?}", bit, bc, most_common_bit, values);
-        values = values.iter().filter(|v| (*v >> bit) & 1 == most_common_bit).cloned().collect();
-        if bit > 0 {
-            bit -= 1;
+/// Binary trie over the report. Every node knows how many values lie below it,
+/// so bit criteria can be evaluated without rescanning the report.
+struct BitTrie {
+    nodes: Vec<TrieNode>,
+    bits: usize,
+}
+
+#[derive(Clone, Default)]
+struct TrieNode {
+    children: [Option<usize>; 2],
+    count: usize,
+}
+
+impl BitTrie {
+    fn new(bits: usize) -> BitTrie {
+        BitTrie {
+            nodes: vec![TrieNode::default()],
+            bits,
         }
     }
-    // println!("{:?}", values);
-    if values.len() > 1 {
-        panic!("More than one value left!");
+
+    fn from_values(values: &[u32], bits: usize) -> BitTrie {
+        let mut trie = BitTrie::new(bits);
+        for &value in values {
+            trie.insert(value);
+        }
+        trie
     }
-    let oxygen = values.get(0).unwrap();
-    // println!("{}", oxygen);
 
+    fn insert(&mut self, value: u32) {
+        let mut node = 0;
+        self.nodes[node].count += 1;
 
-    let mut values = input.clone();
-    let mut bit = bits - 1;
-    while values.len() > 1 {
-        let least_common_bit = if count_set_bit(&values, bit) * 2 >= values.len() {0} else {1};
-        // println!("{}: {}, {:?}", bit, least_common_bit, values);
-        values = values.iter().filter(|v| (*v >> bit) & 1 == least_common_bit).cloned().collect();
-        if bit > 0 {
-            bit -= 1;
+        for bit in (0..self.bits).rev() {
+            let b = ((value >> bit) & 1) as usize;
+            node = match self.nodes[node].children[b] {
+                Some(child) => child,
+                None => {
+                    self.nodes.push(TrieNode::default());
+                    let child = self.nodes.len() - 1;
+                    self.nodes[node].children[b] = Some(child);
+                    child
+                }
+            };
+            self.nodes[node].count += 1;
         }
-        else {
-            break;
+    }
+
+    fn child_count(&self, node: usize, b: usize) -> usize {
+        self.nodes[node].children[b].map_or(0, |child| self.nodes[child].count)
+    }
+
+    /// Walk from the most significant bit downwards. At each bit, `criterion`
+    /// gets the number of remaining values with a 0 and with a 1 at that
+    /// position and returns the bit to keep. Once a single value remains it is
+    /// followed to its end. The rating is `None` if the criterion selects a bit
+    /// no remaining value has.
+    fn trace_rating<F>(&self, criterion: F) -> RatingTrace
+    where
+        F: Fn(usize, usize) -> u32,
+    {
+        let mut node = 0;
+        let mut value = 0;
+        let mut survivors = Vec::new();
+
+        for bit in (0..self.bits).rev() {
+            let zeros = self.child_count(node, 0);
+            let ones = self.child_count(node, 1);
+
+            let b = if self.nodes[node].count == 1 {
+                if ones == 1 {1} else {0}
+            } else {
+                let b = criterion(zeros, ones);
+                survivors.push(if b == 1 {ones} else {zeros});
+                b
+            };
+
+            match self.nodes[node].children[b as usize] {
+                Some(child) => node = child,
+                None => return RatingTrace { rating: None, survivors },
+            }
+            value |= b << bit;
         }
+
+        RatingTrace { rating: Some(value), survivors }
+    }
+
+    fn find_rating<F>(&self, criterion: F) -> Option<u32>
+    where
+        F: Fn(usize, usize) -> u32,
+    {
+        self.trace_rating(criterion).rating
+    }
+
+    fn oxygen_generator_rating(&self) -> Option<u32> {
+        self.find_rating(oxygen_criterion)
     }
-    // println!("{:?}", values);
-    if values.len() > 1 {
-        panic!("More than one value left!");
+
+    fn co2_scrubber_rating(&self) -> Option<u32> {
+        self.find_rating(co2_criterion)
     }
-    let co2 = values.get(0).unwrap();
-    
+}
+
+fn oxygen_criterion(zeros: usize, ones: usize) -> u32 {
+    if ones >= zeros {1} else {0}
+}
+
+fn co2_criterion(zeros: usize, ones: usize) -> u32 {
+    if ones >= zeros {0} else {1}
+}
+
+/// Result of a bit criteria search together with the number of candidates
+/// that survived each filtering round.
+#[derive(Debug)]
+struct RatingTrace {
+    rating: Option<u32>,
+    survivors: Vec<usize>,
+}
+
+fn part2(input: &[u32], bits: usize) -> u32 {
+    let trie = BitTrie::from_values(input, bits);
+
+    let oxygen = trie.oxygen_generator_rating().expect("No oxygen generator rating found!");
+    let co2 = trie.co2_scrubber_rating().expect("No CO2 scrubber rating found!");
+
     oxygen * co2
 }
 
+/// Counts of ones and zeros at one bit position. Positions are counted from
+/// the left, as they appear in the report.
+#[derive(Debug)]
+struct BitStats {
+    position: usize,
+    ones: usize,
+    zeros: usize,
+}
+
+impl BitStats {
+    fn ones_share(&self) -> f64 {
+        self.ones as f64 / (self.ones + self.zeros).max(1) as f64
+    }
+
+    fn zeros_share(&self) -> f64 {
+        self.zeros as f64 / (self.ones + self.zeros).max(1) as f64
+    }
+
+    fn is_tie(&self) -> bool {
+        self.ones == self.zeros
+    }
+}
+
+#[derive(Debug)]
+struct DiagnosticReport {
+    total: usize,
+    bit_stats: Vec<BitStats>,
+    oxygen: RatingTrace,
+    co2: RatingTrace,
+}
+
+impl DiagnosticReport {
+    fn new(input: &[u32], bits: usize) -> DiagnosticReport {
+        let bit_stats = (0..bits)
+            .map(|position| {
+                let ones = count_set_bit(input, bits - 1 - position);
+                BitStats { position, ones, zeros: input.len() - ones }
+            })
+            .collect();
+
+        let trie = BitTrie::from_values(input, bits);
+
+        DiagnosticReport {
+            total: input.len(),
+            bit_stats,
+            oxygen: trie.trace_rating(oxygen_criterion),
+            co2: trie.trace_rating(co2_criterion),
+        }
+    }
+
+    fn to_table(&self) -> String {
+        let mut table = format!("Diagnostic report with {} values\n\n", self.total);
+        table += "position     ones    zeros   ones %  zeros %  tie\n";
+        for stats in &self.bit_stats {
+            table += &format!(
+                "{:>8} {:>8} {:>8} {:>8.2} {:>8.2}  {}\n",
+                stats.position,
+                stats.ones,
+                stats.zeros,
+                100.0 * stats.ones_share(),
+                100.0 * stats.zeros_share(),
+                if stats.is_tie() {"yes"} else {"no"},
+            );
+        }
+
+        for (name, trace) in [("oxygen generator", &self.oxygen), ("CO2 scrubber", &self.co2)] {
+            let rating = match trace.rating {
+                Some(rating) => rating.to_string(),
+                None => "none".to_string(),
+            };
+            let survivors: Vec<String> = trace.survivors.iter().map(|n| n.to_string()).collect();
+            table += &format!(
+                "\n{} rating: {}\nsurvivors per round: {}\n",
+                name, rating, survivors.join(" -> ")
+            );
+        }
+
+        table
+    }
+
+    fn to_json(&self) -> String {
+        let bit_stats: Vec<String> = self
+            .bit_stats
+            .iter()
+            .map(|stats| {
+                format!(
+                    "{{\"position\": {}, \"ones\": {}, \"zeros\": {}, \"ones_share\": {:.4}, \"zeros_share\": {:.4}, \"tie\": {}}}",
+                    stats.position,
+                    stats.ones,
+                    stats.zeros,
+                    stats.ones_share(),
+                    stats.zeros_share(),
+                    stats.is_tie(),
+                )
+            })
+            .collect();
+
+        let trace_json = |trace: &RatingTrace| {
+            let rating = match trace.rating {
+                Some(rating) => rating.to_string(),
+                None => "null".to_string(),
+            };
+            let survivors: Vec<String> = trace.survivors.iter().map(|n| n.to_string()).collect();
+            format!("{{\"rating\": {}, \"survivors\": [{}]}}", rating, survivors.join(", "))
+        };
+
+        format!(
+            "{{\"total\": {}, \"bits\": [{}], \"oxygen\": {}, \"co2\": {}}}",
+            self.total,
+            bit_stats.join(", "),
+            trace_json(&self.oxygen),
+            trace_json(&self.co2),
+        )
+    }
+}
+
 fn main() {
     // let input = read_input("inputs/02.txt");
     let (input, bits) = read_input("inputs/03.txt");
 
+    // Pass "table" or "json" to print the per-bit diagnostic report instead.
+    match std::env::args().nth(1).as_deref() {
+        Some("table") => {
+            print!("{}", DiagnosticReport::new(&input, bits).to_table());
+            return;
+        }
+        Some("json") => {
+            println!("{}", DiagnosticReport::new(&input, bits).to_json());
+            return;
+        }
+        _ => {}
+    }
+
     let solution1 = part1(&input, bits);
     println!("Part 1: Product of gamma and epsilon rate: {}", solution1);
 
@@ -123,4 +338,36 @@ mod tests03 {
 
         assert_eq!(solution, 230);
     }
+
+    #[test]
+    fn test04() {
+        let (input, bits) = read_input("test_inputs/03_01.txt");
+        let trie = BitTrie::from_values(&input, bits);
+
+        assert_eq!(trie.oxygen_generator_rating(), Some(23));
+        assert_eq!(trie.co2_scrubber_rating(), Some(10));
+    }
+
+    #[test]
+    fn test05() {
+        let (input, bits) = read_input("inputs/03.txt");
+        let solution = part2(&input, bits);
+
+        assert_eq!(solution, 4636702);
+    }
+
+    #[test]
+    fn test06() {
+        let (input, bits) = read_input("test_inputs/03_01.txt");
+        let report = DiagnosticReport::new(&input, bits);
+
+        let ones: Vec<usize> = report.bit_stats.iter().map(|s| s.ones).collect();
+        assert_eq!(ones, vec![7, 5, 8, 7, 5]);
+        assert!(report.bit_stats.iter().all(|s| !s.is_tie()));
+
+        assert_eq!(report.oxygen.rating, Some(23));
+        assert_eq!(report.oxygen.survivors, vec![7, 4, 3, 2, 1]);
+        assert_eq!(report.co2.rating, Some(10));
+        assert_eq!(report.co2.survivors, vec![5, 2, 1]);
+    }
 }
@@ -1,26 +1,62 @@
-use std::collections::HashSet;
+use adventofcode2021::rng::Rng;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::fmt::{Debug, Display};
 use std::fs::File;
+use std::hash::Hash;
 use std::io::{BufRead, BufReader};
+use std::str::FromStr;
 
-type BingoNumber = u8;
-const BINGO_ROWS: usize = 5;
+/// Anything that can be drawn and written on a board. The score is summed up
+/// as `u64`, so every unsigned integer type up to `u64` will do.
+trait BingoNumber: Copy + Eq + Hash + Debug + Display + FromStr + Into<u64> {}
 
-#[derive(Debug)]
-struct Board {
-    board: Vec<Vec<BingoNumber>>,
+impl<T: Copy + Eq + Hash + Debug + Display + FromStr + Into<u64>> BingoNumber for T {}
+
+/// Rules of a game. Without a `shape`, boards may have any size as long as
+/// all boards of a game have the same one. Diagonals only exist on square
+/// boards and are ignored otherwise.
+#[derive(Debug, Clone, Default)]
+struct BingoRules {
+    shape: Option<(usize, usize)>,
+    diagonals: bool,
 }
 
 #[derive(Debug)]
-enum BoardError {InvalidDimensions}
+struct Board<N: BingoNumber = u8> {
+    board: Vec<Vec<N>>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum BoardError {
+    /// Shapes are given as (rows, columns).
+    InvalidDimensions {
+        expected: (usize, usize),
+        actual: (usize, usize),
+    },
+    /// A value on the given line of the file (counted from 1) is no valid number.
+    InvalidNumber { line: usize, value: String },
+    MissingNumbers,
+    /// The file cannot be opened or read.
+    Unreadable(String),
+}
+
+impl<N: BingoNumber> Board<N> {
+    fn new(rows: Vec<Vec<N>>, shape: (usize, usize)) -> Result<Board<N>, BoardError> {
+        let (n_rows, n_columns) = shape;
 
-impl Board {
-    fn new(rows: Vec<Vec<BingoNumber>>) -> Result<Board, BoardError> {
-        if rows.len() != BINGO_ROWS {
-            return Err(BoardError::InvalidDimensions);
+        if rows.len() != n_rows || n_rows == 0 || n_columns == 0 {
+            let columns = rows.first().map_or(0, |row| row.len());
+            return Err(BoardError::InvalidDimensions {
+                expected: shape,
+                actual: (rows.len(), columns),
+            });
         }
         for row in &rows {
-            if row.len() != BINGO_ROWS {
-                return Err(BoardError::InvalidDimensions);
+            if row.len() != n_columns {
+                return Err(BoardError::InvalidDimensions {
+                    expected: shape,
+                    actual: (rows.len(), row.len()),
+                });
             }
         }
 
@@ -28,111 +64,1153 @@ impl Board {
             board: rows,
         })
     }
+
+    fn rows(&self) -> usize {
+        self.board.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.board[0].len()
+    }
+
+    fn is_square(&self) -> bool {
+        self.rows() == self.columns()
+    }
+
+    /// All lines that can win under `rules` together with their numbers.
+    fn lines(&self, rules: &BingoRules) -> Vec<(BingoLine, Vec<N>)> {
+        let mut lines: Vec<(BingoLine, Vec<N>)> = Vec::new();
+        for (row, numbers) in self.board.iter().enumerate() {
+            lines.push((BingoLine::Row(row), numbers.clone()));
+        }
+        for column in 0..self.columns() {
+            let numbers = self.board.iter().map(|row| row[column]).collect();
+            lines.push((BingoLine::Column(column), numbers));
+        }
+        if rules.diagonals && self.is_square() {
+            let size = self.rows();
+            lines.push((BingoLine::Diagonal, (0..size).map(|i| self.board[i][i]).collect()));
+            lines.push((BingoLine::AntiDiagonal, (0..size).map(|i| self.board[i][size - 1 - i]).collect()));
+        }
+        lines
+    }
+}
+
+/// A completed line on a board.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+enum BingoLine {
+    Row(usize),
+    Column(usize),
+    /// From the top left to the bottom right corner.
+    Diagonal,
+    /// From the top right to the bottom left corner.
+    AntiDiagonal,
+}
+
+impl BingoLine {
+    fn contains(&self, row: usize, column: usize, size: usize) -> bool {
+        match *self {
+            BingoLine::Row(r) => row == r,
+            BingoLine::Column(c) => column == c,
+            BingoLine::Diagonal => row == column,
+            BingoLine::AntiDiagonal => row + column + 1 == size,
+        }
+    }
 }
 
-fn board_wins(board: &Board, numbers: &HashSet<BingoNumber>) -> bool {
-    let has_full_row = board.board.iter().any(|row| {
+/// Find a completed line. Rows are checked before columns and diagonals.
+#[cfg(test)]
+fn winning_line<N: BingoNumber>(
+    board: &Board<N>,
+    numbers: &HashSet<N>,
+    rules: &BingoRules,
+) -> Option<BingoLine> {
+    let full_row = board.board.iter().position(|row| {
         row.iter().all(|n| numbers.contains(n))
     });
+    if let Some(row) = full_row {
+        return Some(BingoLine::Row(row));
+    }
 
-    let has_full_column = (0..BINGO_ROWS).any(|column| {
+    let full_column = (0..board.columns()).find(|&column| {
         board.board.iter().all(|row| numbers.contains(&row[column]))
     });
+    if let Some(column) = full_column {
+        return Some(BingoLine::Column(column));
+    }
+
+    if rules.diagonals && board.is_square() {
+        let size = board.rows();
+        if (0..size).all(|i| numbers.contains(&board.board[i][i])) {
+            return Some(BingoLine::Diagonal);
+        }
+        if (0..size).all(|i| numbers.contains(&board.board[i][size - 1 - i])) {
+            return Some(BingoLine::AntiDiagonal);
+        }
+    }
+
+    None
+}
 
-    has_full_row || has_full_column
+#[cfg(test)]
+fn board_wins<N: BingoNumber>(board: &Board<N>, numbers: &HashSet<N>, rules: &BingoRules) -> bool {
+    winning_line(board, numbers, rules).is_some()
 }
 
-fn calc_score(board: &Board, marked: &HashSet<BingoNumber>) -> u32 {
+fn calc_score<N: BingoNumber>(board: &Board<N>, marked: &HashSet<N>) -> u64 {
     board.board.iter().
         map(|row| {
-            row.iter().filter(|n| !marked.contains(n)).map(|n| *n as u32).sum::<u32>()
+            row.iter().filter(|n| !marked.contains(n)).map(|&n| n.into()).sum::<u64>()
         })
         .sum()
 }
 
-fn read_input(filename: &str) -> (Vec<BingoNumber>, Vec<Board>) {
-    let file = File::open(filename).expect("Cannot open file");
-    let reader = BufReader::new(file);
+fn parse_numbers<N: BingoNumber>(
+    line: &str,
+    line_number: usize,
+    separator: Option<char>,
+) -> Result<Vec<N>, BoardError> {
+    let parse = |s: &str| {
+        s.trim().parse().map_err(|_| BoardError::InvalidNumber {
+            line: line_number,
+            value: s.to_string(),
+        })
+    };
+    match separator {
+        Some(separator) => line.split(separator).map(parse).collect(),
+        None => line.split_whitespace().map(parse).collect(),
+    }
+}
 
-    let mut lines = reader.lines();
+type ParsedBoard<N> = Result<Board<N>, BoardError>;
 
-    let numbers: Vec<BingoNumber>;
-    let mut boards: Vec<Board> = Vec::new();
+/// Read the drawn numbers and try to parse every board on its own. Boards are
+/// separated by empty lines and must all have the shape demanded by `rules`
+/// or, without one, the shape of the first board.
+fn read_boards<N: BingoNumber>(
+    filename: &str,
+    rules: &BingoRules,
+) -> Result<(Vec<N>, Vec<ParsedBoard<N>>), BoardError> {
+    let unreadable = |error: std::io::Error| BoardError::Unreadable(format!("{}: {}", filename, error));
+    let file = File::open(filename).map_err(unreadable)?;
+    let lines: Vec<String> = BufReader::new(file).lines().collect::<Result<_, _>>().map_err(unreadable)?;
 
-    if let Some(Ok(line)) = lines.next() {
-        numbers = line
-            .split(',')
-            .map(|s| s.parse().expect(&format!("Cannot parse {}", s)))
-            .collect();
-    } else {
-        panic!("Missing first line!");
-    }
-    lines
-        .next()
-        .expect("Missing line before first boards")
-        .unwrap();
-
-    let mut rows: Vec<Vec<BingoNumber>> = Vec::new();
-    for line in lines {
-        if let Ok(line) = line {
-            if line.len() > 1 {
-                let row = line
-                    .split_whitespace()
-                    .map(|s| {
-                        s.parse()
-                            .expect(&format!("Cannot parse {:?} in {:?}", s, &line))
-                    })
-                    .collect();
-                rows.push(row);
+    let mut lines = lines.into_iter().enumerate();
 
-                if rows.len() == BINGO_ROWS {
-                    let board = Board::new(rows).unwrap();
-                    boards.push(board);
-                    rows = Vec::new();
-                }
+    let numbers: Vec<N> = match lines.next() {
+        Some((i, line)) => parse_numbers(&line, i + 1, Some(','))?,
+        None => return Err(BoardError::MissingNumbers),
+    };
+
+    // Group the remaining lines into blocks, remembering their line numbers.
+    let mut blocks: Vec<Vec<(usize, String)>> = Vec::new();
+    let mut block: Vec<(usize, String)> = Vec::new();
+    for (i, line) in lines {
+        if line.trim().is_empty() {
+            if !block.is_empty() {
+                blocks.push(block);
+                block = Vec::new();
             }
+        } else {
+            block.push((i + 1, line));
         }
     }
+    if !block.is_empty() {
+        blocks.push(block);
+    }
 
-    (numbers, boards)
+    let shape = match (rules.shape, blocks.first()) {
+        (Some(shape), _) => shape,
+        (None, Some(first)) => (first.len(), first[0].1.split_whitespace().count()),
+        (None, None) => (0, 0),
+    };
+
+    let boards = blocks
+        .iter()
+        .map(|block| {
+            let rows = block
+                .iter()
+                .map(|(i, line)| parse_numbers(line, *i, None))
+                .collect::<Result<Vec<Vec<N>>, BoardError>>()?;
+            Board::new(rows, shape)
+        })
+        .collect();
+
+    Ok((numbers, boards))
+}
+
+/// Read the drawn numbers and all boards, failing on the first malformed board.
+fn read_input_with_rules<N: BingoNumber>(
+    filename: &str,
+    rules: &BingoRules,
+) -> Result<(Vec<N>, Vec<Board<N>>), BoardError> {
+    let (numbers, boards) = read_boards(filename, rules)?;
+    let boards = boards.into_iter().collect::<Result<Vec<Board<N>>, BoardError>>()?;
+
+    Ok((numbers, boards))
+}
+
+fn read_input(filename: &str) -> (Vec<u8>, Vec<Board>) {
+    read_input_with_rules(filename, &BingoRules::default()).unwrap()
 }
 
-fn play_bingo(numbers: &Vec<BingoNumber>, boards: &Vec<Board>, break_on_first: bool) -> u32 {
-    let mut marked_numbers: HashSet<BingoNumber> = HashSet::new();
-    let mut remaining_boards: HashSet<usize> = HashSet::from_iter(0..boards.len());
+/// How and when a board has won.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Win<N: BingoNumber> {
+    /// Number of drawn numbers up to and including the winning one.
+    turn: usize,
+    number: N,
+    line: BingoLine,
+    unmarked_sum: u64,
+    /// Boards winning on the same turn are ranked by their position in the input.
+    rank: usize,
+}
+
+impl<N: BingoNumber> Win<N> {
+    fn score(&self) -> u64 {
+        self.number.into() * self.unmarked_sum
+    }
+}
 
-    let mut last_number = 0;
-    let mut score = 0;
+/// Outcome of a game for every board, in input order. Boards that never
+/// win have no `Win`.
+#[derive(Debug)]
+struct BingoGame<N: BingoNumber> {
+    results: Vec<Option<Win<N>>>,
+}
+
+impl<N: BingoNumber> BingoGame<N> {
+    /// Indices of all boards ordered by rank, followed by the boards that never won.
+    fn ranking(&self) -> Vec<usize> {
+        let mut boards: Vec<usize> = (0..self.results.len()).collect();
+        boards.sort_by_key(|&i| self.results[i].as_ref().map_or(usize::MAX, |win| win.rank));
+        boards
+    }
+
+    fn winners(&self) -> impl Iterator<Item = &Win<N>> {
+        self.results.iter().flatten()
+    }
+
+    fn first_winner(&self) -> Option<&Win<N>> {
+        self.winners().min_by_key(|win| win.rank)
+    }
+
+    fn last_winner(&self) -> Option<&Win<N>> {
+        self.winners().max_by_key(|win| win.rank)
+    }
+}
+
+/// Reference implementation which rescans every remaining board after each draw.
+#[cfg(test)]
+fn play_bingo_naive<N: BingoNumber>(numbers: &[N], boards: &[Board<N>], rules: &BingoRules) -> BingoGame<N> {
+    let mut marked_numbers: HashSet<N> = HashSet::new();
+    let mut results: Vec<Option<Win<N>>> = vec![None; boards.len()];
+    let mut rank = 0;
 
-    'outer: for number in numbers {
+    for (turn, number) in numbers.iter().enumerate() {
+        if rank == boards.len() {
+            break;
+        }
         marked_numbers.insert(*number);
 
         for (i, board) in boards.iter().enumerate() {
-            if remaining_boards.contains(&i) && board_wins(board, &marked_numbers) {
-                remaining_boards.remove(&i);
-                last_number = *number;
-                score = calc_score(board, &marked_numbers);
-                if break_on_first {
-                    break 'outer;
+            if results[i].is_some() {
+                continue;
+            }
+            if let Some(line) = winning_line(board, &marked_numbers, rules) {
+                rank += 1;
+                results[i] = Some(Win {
+                    turn: turn + 1,
+                    number: *number,
+                    line,
+                    unmarked_sum: calc_score(board, &marked_numbers),
+                    rank,
+                });
+            }
+        }
+    }
+
+    BingoGame { results }
+}
+
+/// Cells (board, row, column) at which each number appears.
+struct BingoIndex<N: BingoNumber> {
+    cells: HashMap<N, Vec<(usize, usize, usize)>>,
+}
+
+impl<N: BingoNumber> BingoIndex<N> {
+    fn new(boards: &[Board<N>]) -> BingoIndex<N> {
+        let mut cells: HashMap<N, Vec<(usize, usize, usize)>> = HashMap::new();
+        for (i, board) in boards.iter().enumerate() {
+            for (row, numbers) in board.board.iter().enumerate() {
+                for (column, &number) in numbers.iter().enumerate() {
+                    cells.entry(number).or_default().push((i, row, column));
+                }
+            }
+        }
+        BingoIndex { cells }
+    }
+
+    fn cells(&self, number: &N) -> &[(usize, usize, usize)] {
+        self.cells.get(number).map_or(&[], |cells| &cells[..])
+    }
+}
+
+/// Number of marked cells on every line of a board.
+struct LineCounters {
+    rows: Vec<usize>,
+    columns: Vec<usize>,
+    diagonal: usize,
+    anti_diagonal: usize,
+}
+
+impl LineCounters {
+    fn new<N: BingoNumber>(board: &Board<N>) -> LineCounters {
+        LineCounters {
+            rows: vec![0; board.rows()],
+            columns: vec![0; board.columns()],
+            diagonal: 0,
+            anti_diagonal: 0,
+        }
+    }
+
+    /// Mark a cell and return the lines completed by it, in the order
+    /// `winning_line` would check them.
+    fn mark<N: BingoNumber>(&mut self, board: &Board<N>, row: usize, column: usize, rules: &BingoRules) -> Vec<BingoLine> {
+        let mut completed = Vec::new();
+
+        self.rows[row] += 1;
+        if self.rows[row] == board.columns() {
+            completed.push(BingoLine::Row(row));
+        }
+        self.columns[column] += 1;
+        if self.columns[column] == board.rows() {
+            completed.push(BingoLine::Column(column));
+        }
+
+        if rules.diagonals && board.is_square() {
+            let size = board.rows();
+            if row == column {
+                self.diagonal += 1;
+                if self.diagonal == size {
+                    completed.push(BingoLine::Diagonal);
+                }
+            }
+            if row + column == size - 1 {
+                self.anti_diagonal += 1;
+                if self.anti_diagonal == size {
+                    completed.push(BingoLine::AntiDiagonal);
+                }
+            }
+        }
+
+        completed
+    }
+}
+
+/// Play the game on an index of all numbers. Each draw only touches the
+/// cells holding the drawn number instead of rescanning all boards.
+fn play_bingo<N: BingoNumber>(numbers: &[N], boards: &[Board<N>], rules: &BingoRules) -> BingoGame<N> {
+    let index = BingoIndex::new(boards);
+    let mut counters: Vec<LineCounters> = boards.iter().map(LineCounters::new).collect();
+    let mut unmarked_sums: Vec<u64> = boards
+        .iter()
+        .map(|board| calc_score(board, &HashSet::new()))
+        .collect();
+
+    let mut drawn: HashSet<N> = HashSet::new();
+    let mut results: Vec<Option<Win<N>>> = vec![None; boards.len()];
+    let mut rank = 0;
+
+    for (turn, number) in numbers.iter().enumerate() {
+        if rank == boards.len() {
+            break;
+        }
+        if !drawn.insert(*number) {
+            continue;
+        }
+
+        // A number may appear more than once on a board, so winners are only
+        // decided after all of its cells have been marked.
+        let mut completed: BTreeMap<usize, BingoLine> = BTreeMap::new();
+        for &(i, row, column) in index.cells(number) {
+            if results[i].is_some() {
+                continue;
+            }
+            unmarked_sums[i] -= (*number).into();
+            for line in counters[i].mark(&boards[i], row, column, rules) {
+                let best = completed.entry(i).or_insert(line);
+                *best = (*best).min(line);
+            }
+        }
+
+        for (i, line) in completed {
+            rank += 1;
+            results[i] = Some(Win {
+                turn: turn + 1,
+                number: *number,
+                line,
+                unmarked_sum: unmarked_sums[i],
+                rank,
+            });
+        }
+    }
+
+    BingoGame { results }
+}
+
+/// Estimated value with a 95% confidence interval.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct Estimate {
+    value: f64,
+    low: f64,
+    high: f64,
+}
+
+impl Estimate {
+    const Z: f64 = 1.96;
+
+    /// Wilson score interval of a proportion.
+    fn proportion(successes: usize, trials: usize) -> Estimate {
+        if trials == 0 {
+            return Estimate { value: 0.0, low: 0.0, high: 1.0 };
+        }
+        let n = trials as f64;
+        let p = successes as f64 / n;
+        let z2 = Self::Z * Self::Z;
+        let center = (p + z2 / (2.0 * n)) / (1.0 + z2 / n);
+        let half_width = Self::Z / (1.0 + z2 / n) * (p * (1.0 - p) / n + z2 / (4.0 * n * n)).sqrt();
+
+        Estimate { value: p, low: center - half_width, high: center + half_width }
+    }
+
+    /// Normal approximation interval of a mean.
+    fn mean(sum: f64, sum_of_squares: f64, samples: usize) -> Option<Estimate> {
+        if samples == 0 {
+            return None;
+        }
+        let n = samples as f64;
+        let mean = sum / n;
+        let variance = if samples > 1 {
+            ((sum_of_squares - n * mean * mean) / (n - 1.0)).max(0.0)
+        } else {
+            0.0
+        };
+        let half_width = Self::Z * (variance / n).sqrt();
+
+        Some(Estimate { value: mean, low: mean - half_width, high: mean + half_width })
+    }
+}
+
+/// Monte Carlo estimates for a single board.
+#[derive(Debug, PartialEq)]
+struct WinOdds {
+    first: Estimate,
+    last: Estimate,
+    /// Expected winning turn over the trials in which the board won at all.
+    expected_turn: Option<Estimate>,
+    wins: usize,
+}
+
+/// Play `trials` games with random permutations of the drawn numbers. Boards
+/// winning on the same turn are ranked as in `play_bingo`, i.e. by position.
+fn estimate_win_odds<N: BingoNumber>(
+    numbers: &[N],
+    boards: &[Board<N>],
+    rules: &BingoRules,
+    trials: usize,
+    seed: u64,
+) -> Vec<WinOdds> {
+    let mut rng = Rng::new(seed);
+    let mut numbers = numbers.to_vec();
+
+    let mut first = vec![0; boards.len()];
+    let mut last = vec![0; boards.len()];
+    let mut wins = vec![0; boards.len()];
+    let mut turn_sums = vec![0.0; boards.len()];
+    let mut turn_square_sums = vec![0.0; boards.len()];
+
+    for _trial in 0..trials {
+        rng.shuffle(&mut numbers);
+        let game = play_bingo(&numbers, boards, rules);
+
+        let ranking = game.ranking();
+        let winners = game.winners().count();
+        if winners > 0 {
+            first[ranking[0]] += 1;
+            last[ranking[winners - 1]] += 1;
+        }
+
+        for (i, result) in game.results.iter().enumerate() {
+            if let Some(win) = result {
+                wins[i] += 1;
+                turn_sums[i] += win.turn as f64;
+                turn_square_sums[i] += (win.turn * win.turn) as f64;
+            }
+        }
+    }
+
+    (0..boards.len())
+        .map(|i| WinOdds {
+            first: Estimate::proportion(first[i], trials),
+            last: Estimate::proportion(last[i], trials),
+            expected_turn: Estimate::mean(turn_sums[i], turn_square_sums[i], wins[i]),
+            wins: wins[i],
+        })
+        .collect()
+}
+
+fn print_win_odds(odds: &[WinOdds]) {
+    println!("board  P(first)          95% CI   P(last)           95% CI   E[turn]           95% CI");
+    for (i, board_odds) in odds.iter().enumerate() {
+        let turn = match board_odds.expected_turn {
+            Some(turn) => format!("{:>8.2} [{:>6.2}, {:>6.2}]", turn.value, turn.low, turn.high),
+            None => format!("{:>8}", "-"),
+        };
+        println!(
+            "{:>5} {:>9.4} [{:.4}, {:.4}] {:>9.4} [{:.4}, {:.4}] {}",
+            i,
+            board_odds.first.value,
+            board_odds.first.low,
+            board_odds.first.high,
+            board_odds.last.value,
+            board_odds.last.low,
+            board_odds.last.high,
+            turn
+        );
+    }
+}
+
+/// Set of numbers as a bitset over the distinct numbers of all boards.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct NumberSet {
+    words: Vec<u64>,
+}
+
+impl NumberSet {
+    fn new(size: usize) -> NumberSet {
+        NumberSet { words: vec![0; size.div_ceil(64)] }
+    }
+
+    fn insert(&mut self, i: usize) {
+        self.words[i / 64] |= 1 << (i % 64);
+    }
+
+    fn contains(&self, i: usize) -> bool {
+        self.words[i / 64] & (1 << (i % 64)) != 0
+    }
+
+    fn len(&self) -> usize {
+        self.words.iter().map(|w| w.count_ones() as usize).sum()
+    }
+
+    fn union(&self, other: &NumberSet) -> NumberSet {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a | b).collect();
+        NumberSet { words }
+    }
+
+    fn intersection(&self, other: &NumberSet) -> NumberSet {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a & b).collect();
+        NumberSet { words }
+    }
+
+    fn difference(&self, other: &NumberSet) -> NumberSet {
+        let words = self.words.iter().zip(&other.words).map(|(a, b)| a & !b).collect();
+        NumberSet { words }
+    }
+
+    /// Number of elements of `self` missing from `other`.
+    fn missing_from(&self, other: &NumberSet) -> usize {
+        self.words.iter().zip(&other.words).map(|(a, b)| (a & !b).count_ones() as usize).sum()
+    }
+
+    fn is_subset(&self, other: &NumberSet) -> bool {
+        self.missing_from(other) == 0
+    }
+
+    fn iter(&self) -> impl Iterator<Item = usize> + '_ {
+        (0..self.words.len() * 64).filter(|&i| self.contains(i))
+    }
+
+    fn first(&self) -> Option<usize> {
+        self.iter().next()
+    }
+}
+
+/// A sequence of draws found by the adversarial search. It is `optimal` if
+/// the search finished without hitting its node limit.
+#[derive(Debug)]
+struct DrawPlan<N: BingoNumber> {
+    draws: Vec<N>,
+    optimal: bool,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum PlanError {
+    /// No draw order works. `blockers` are the boards that prevent it, as far
+    /// as they are known.
+    Impossible { blockers: Vec<usize> },
+    /// The node limit was hit before any plan was found.
+    SearchLimit,
+}
+
+/// All lines of all boards as sets over the distinct numbers of the boards.
+struct LineSets<N: BingoNumber> {
+    numbers: Vec<N>,
+    lines: Vec<Vec<NumberSet>>,
+}
+
+impl<N: BingoNumber> LineSets<N> {
+    fn new(boards: &[Board<N>], rules: &BingoRules) -> LineSets<N> {
+        let mut numbers: Vec<N> = Vec::new();
+        let mut ids: HashMap<N, usize> = HashMap::new();
+        for board in boards {
+            for &number in board.board.iter().flatten() {
+                ids.entry(number).or_insert_with(|| {
+                    numbers.push(number);
+                    numbers.len() - 1
+                });
+            }
+        }
+
+        let lines = boards
+            .iter()
+            .map(|board| {
+                board
+                    .lines(rules)
+                    .into_iter()
+                    .map(|(_, line)| {
+                        let mut set = NumberSet::new(numbers.len());
+                        for number in line {
+                            set.insert(ids[&number]);
+                        }
+                        set
+                    })
+                    .collect()
+            })
+            .collect();
+
+        LineSets { numbers, lines }
+    }
+
+    fn empty(&self) -> NumberSet {
+        NumberSet::new(self.numbers.len())
+    }
+
+    fn wins(&self, board: usize, drawn: &NumberSet) -> bool {
+        self.lines[board].iter().any(|line| line.is_subset(drawn))
+    }
+
+    /// Draws of all numbers in `first` followed by `last`.
+    fn draws(&self, first: &NumberSet, last: usize) -> Vec<N> {
+        first
+            .iter()
+            .filter(|&i| i != last)
+            .chain(std::iter::once(last))
+            .map(|i| self.numbers[i])
+            .collect()
+    }
+}
+
+/// Find the shortest draw sequence after which `target` is the first winner.
+/// It always consists of a single line of the target, drawn so that every
+/// other board completing a line within it does so only on the last draw and
+/// ranks behind the target.
+fn plan_first_win<N: BingoNumber>(
+    boards: &[Board<N>],
+    rules: &BingoRules,
+    target: usize,
+) -> Result<DrawPlan<N>, PlanError> {
+    let sets = LineSets::new(boards, rules);
+    let mut candidates: Vec<&NumberSet> = sets.lines[target].iter().collect();
+    candidates.sort_by_key(|line| line.len());
+
+    let mut blockers: Vec<usize> = Vec::new();
+    for line in candidates {
+        // The last draw must be part of every line of another board covered
+        // by this one, and all those boards must rank behind the target.
+        let mut last_draws = line.clone();
+        let mut blocked = false;
+        for (board, board_lines) in sets.lines.iter().enumerate() {
+            if board == target {
+                continue;
+            }
+            for other in board_lines.iter().filter(|other| other.is_subset(line)) {
+                last_draws = last_draws.intersection(other);
+                if board < target || last_draws.len() == 0 {
+                    blockers.push(board);
+                    blocked = true;
                 }
             }
         }
+
+        if !blocked {
+            let last = last_draws.first().unwrap();
+            return Ok(DrawPlan { draws: sets.draws(line, last), optimal: true });
+        }
+    }
+
+    blockers.sort_unstable();
+    blockers.dedup();
+    Err(PlanError::Impossible { blockers })
+}
+
+struct LastWinSearch<'a, N: BingoNumber> {
+    sets: &'a LineSets<N>,
+    target: usize,
+    nodes: usize,
+    node_limit: usize,
+    best: Option<LastWinPlan>,
+}
+
+/// Draws after which all other boards have won, the target's remaining
+/// draws and which of those comes last.
+struct LastWinPlan {
+    before: NumberSet,
+    target_draws: NumberSet,
+    last: usize,
+}
+
+impl LastWinPlan {
+    fn len(&self) -> usize {
+        self.before.len() + self.target_draws.len()
+    }
+}
+
+impl<'a, N: BingoNumber> LastWinSearch<'a, N> {
+    /// Cheapest way for the target to finish after `drawn`. The `pending`
+    /// boards rank ahead of the target and may finish together with it.
+    fn finish_target(&self, drawn: &NumberSet, pending: &[usize]) -> Option<LastWinPlan> {
+        let target_lines = &self.sets.lines[self.target];
+        let mut best: Option<LastWinPlan> = None;
+
+        for line in target_lines {
+            let target_draws = line.difference(drawn);
+            if best.as_ref().is_some_and(|b| b.target_draws.len() <= target_draws.len()) {
+                continue;
+            }
+            // Every target line completed by these draws must contain the
+            // last one, so that the target does not finish early.
+            let all = drawn.union(line);
+            if !pending.iter().all(|&board| self.sets.wins(board, &all)) {
+                continue;
+            }
+            let mut last_draws = target_draws.clone();
+            for other in target_lines.iter().filter(|other| other.is_subset(&all)) {
+                last_draws = last_draws.intersection(other);
+            }
+            if let Some(last) = last_draws.first() {
+                best = Some(LastWinPlan { before: drawn.clone(), target_draws, last });
+            }
+        }
+
+        best
+    }
+
+    fn search(&mut self, drawn: &NumberSet) {
+        if self.nodes >= self.node_limit {
+            return;
+        }
+        self.nodes += 1;
+
+        if self.sets.wins(self.target, drawn) {
+            return;
+        }
+
+        let remaining: Vec<usize> = (0..self.sets.lines.len())
+            .filter(|&board| board != self.target && !self.sets.wins(board, drawn))
+            .collect();
+
+        // Boards ranking ahead of the target may still win on its last draw.
+        if remaining.iter().all(|&board| board < self.target) {
+            if let Some(plan) = self.finish_target(drawn, &remaining) {
+                if self.best.as_ref().is_none_or(|best| plan.len() < best.len()) {
+                    self.best = Some(plan);
+                }
+            }
+            if remaining.is_empty() {
+                return;
+            }
+        }
+
+        // Every remaining board needs at least the missing numbers of its
+        // cheapest line, and the target needs at least one more draw.
+        let cheapest = |board: usize| {
+            self.sets.lines[board].iter().map(|line| line.missing_from(drawn)).min().unwrap_or(0)
+        };
+        let (board, bound) = remaining
+            .iter()
+            .map(|&board| (board, cheapest(board)))
+            .max_by_key(|&(_, missing)| missing)
+            .unwrap();
+        if let Some(best) = &self.best {
+            if drawn.len() + bound.max(1) >= best.len() {
+                return;
+            }
+        }
+
+        let mut lines: Vec<&NumberSet> = self.sets.lines[board].iter().collect();
+        lines.sort_by_key(|line| line.missing_from(drawn));
+        for line in lines {
+            self.search(&drawn.union(line));
+        }
+    }
+}
+
+/// Find the shortest draw sequence after which every board has won and
+/// `target` was the last one. Other boards are scheduled to finish strictly
+/// before the target, except that boards ranking ahead of it on a tie may
+/// finish on the same draw. The branch and bound search over the lines of the other
+/// boards stops after `node_limit` nodes.
+fn plan_last_win<N: BingoNumber>(
+    boards: &[Board<N>],
+    rules: &BingoRules,
+    target: usize,
+    node_limit: usize,
+) -> Result<DrawPlan<N>, PlanError> {
+    let sets = LineSets::new(boards, rules);
+
+    // A board all of whose lines contain a complete line of the target can
+    // never win before it. Only boards ranking behind the target on a tie
+    // are blocked by this.
+    let blockers: Vec<usize> = (0..boards.len())
+        .filter(|&board| {
+            board > target
+                && sets.lines[board]
+                    .iter()
+                    .all(|line| sets.lines[target].iter().any(|t| t.is_subset(line)))
+        })
+        .collect();
+    if !blockers.is_empty() {
+        return Err(PlanError::Impossible { blockers });
+    }
+
+    let mut search = LastWinSearch { sets: &sets, target, nodes: 0, node_limit, best: None };
+    search.search(&sets.empty());
+    let optimal = search.nodes < node_limit;
+
+    match search.best {
+        Some(plan) => {
+            // Draw everything the other boards need first, then the rest of
+            // the target's line.
+            let mut draws: Vec<N> = plan.before.iter().map(|i| sets.numbers[i]).collect();
+            draws.extend(sets.draws(&plan.target_draws, plan.last));
+            Ok(DrawPlan { draws, optimal })
+        }
+        None if optimal => Err(PlanError::Impossible { blockers: Vec::new() }),
+        None => Err(PlanError::SearchLimit),
+    }
+}
+
+/// Replay a plan and check that `target` wins first, or last after all
+/// other boards, exactly on the final draw.
+fn check_plan<N: BingoNumber>(
+    plan: &DrawPlan<N>,
+    boards: &[Board<N>],
+    rules: &BingoRules,
+    target: usize,
+    win_last: bool,
+) -> bool {
+    let game = play_bingo(&plan.draws, boards, rules);
+    let wins_on_last_draw = game.results[target]
+        .as_ref()
+        .is_some_and(|win| win.turn == plan.draws.len());
+
+    if win_last {
+        wins_on_last_draw
+            && game.winners().count() == boards.len()
+            && game.ranking().last() == Some(&target)
+    } else {
+        wins_on_last_draw && game.ranking().first() == Some(&target)
+    }
+}
+
+const STYLE_MARKED: &str = "\x1b[1;32m";
+const STYLE_WINNING_LINE: &str = "\x1b[1;30;43m";
+const STYLE_RESET: &str = "\x1b[0m";
+
+/// Steps through a game one draw at a time and renders it for a terminal.
+/// Malformed boards and boards that can never win are kept as problems
+/// instead of being played.
+struct BingoPlayer<N: BingoNumber> {
+    numbers: Vec<N>,
+    boards: Vec<Board<N>>,
+    /// Position of every playable board in the input.
+    positions: Vec<usize>,
+    problems: Vec<String>,
+    game: BingoGame<N>,
+    turn: usize,
+}
+
+impl<N: BingoNumber> BingoPlayer<N> {
+    fn new(numbers: Vec<N>, parsed_boards: Vec<ParsedBoard<N>>, rules: &BingoRules) -> BingoPlayer<N> {
+        let mut boards = Vec::new();
+        let mut positions = Vec::new();
+        let mut problems = Vec::new();
+        for (i, board) in parsed_boards.into_iter().enumerate() {
+            match board {
+                Ok(board) => {
+                    boards.push(board);
+                    positions.push(i);
+                }
+                Err(error) => problems.push(format!("Board {} is malformed: {:?}", i, error)),
+            }
+        }
+
+        let game = play_bingo(&numbers, &boards, rules);
+        for (j, result) in game.results.iter().enumerate() {
+            if result.is_none() {
+                problems.push(format!("Board {} can never win with the drawn numbers", positions[j]));
+            }
+        }
+
+        BingoPlayer { numbers, boards, positions, problems, game, turn: 0 }
+    }
+
+    /// Draw the next number. Returns false if the game was already finished.
+    fn step(&mut self) -> bool {
+        if self.is_finished() {
+            return false;
+        }
+        self.turn += 1;
+        true
+    }
+
+    fn is_finished(&self) -> bool {
+        self.turn == self.numbers.len() || self.game.winners().all(|win| win.turn <= self.turn)
+    }
+
+    fn win(&self, board: usize) -> Option<&Win<N>> {
+        self.game.results[board].as_ref().filter(|win| win.turn <= self.turn)
+    }
+
+    fn render_board(&self, board: usize, cell_width: usize) -> Vec<String> {
+        let marked: HashSet<N> = self.numbers[..self.turn].iter().cloned().collect();
+        let win = self.win(board);
+        let board = &self.boards[board];
+
+        board
+            .board
+            .iter()
+            .enumerate()
+            .map(|(row, numbers)| {
+                let cells: Vec<String> = numbers
+                    .iter()
+                    .enumerate()
+                    .map(|(column, number)| {
+                        let cell = format!("{:>width$}", number, width = cell_width);
+                        if win.is_some_and(|win| win.line.contains(row, column, board.rows())) {
+                            format!("{}{}{}", STYLE_WINNING_LINE, cell, STYLE_RESET)
+                        } else if marked.contains(number) {
+                            format!("{}{}{}", STYLE_MARKED, cell, STYLE_RESET)
+                        } else {
+                            cell
+                        }
+                    })
+                    .collect();
+                cells.join(" ")
+            })
+            .collect()
+    }
+
+    /// Render all boards side by side, wrapping after `width` characters,
+    /// followed by the scoreboard.
+    fn render(&self, width: usize) -> String {
+        let mut out = String::new();
+
+        let drawn: Vec<String> = self.numbers[..self.turn].iter().map(|n| n.to_string()).collect();
+        out += &format!("Turn {}/{}, drawn: {}\n", self.turn, self.numbers.len(), drawn.join(","));
+        for problem in &self.problems {
+            out += &format!("! {}\n", problem);
+        }
+        out += "\n";
+
+        let cell_width = self
+            .boards
+            .iter()
+            .flat_map(|board| board.board.iter().flatten())
+            .map(|n| n.to_string().len())
+            .max()
+            .unwrap_or(1);
+        let board_width = |board: &Board<N>| (board.columns() * (cell_width + 1)).max(12);
+        let gap = "   ";
+
+        let mut start = 0;
+        while start < self.boards.len() {
+            // Take as many boards as fit into the width, but at least one.
+            let mut end = start + 1;
+            let mut used = board_width(&self.boards[start]);
+            while end < self.boards.len() && used + gap.len() + board_width(&self.boards[end]) <= width {
+                used += gap.len() + board_width(&self.boards[end]);
+                end += 1;
+            }
+
+            let titles: Vec<String> = (start..end)
+                .map(|i| {
+                    let title = match self.win(i) {
+                        Some(win) => format!("Board {} #{}", self.positions[i], win.rank),
+                        None => format!("Board {}", self.positions[i]),
+                    };
+                    format!("{:<width$}", title, width = board_width(&self.boards[i]))
+                })
+                .collect();
+            out += titles.join(gap).trim_end();
+            out += "\n";
+
+            let rendered: Vec<Vec<String>> = (start..end).map(|i| self.render_board(i, cell_width)).collect();
+            let rows = (start..end).map(|i| self.boards[i].rows()).max().unwrap();
+            for row in 0..rows {
+                let line: Vec<String> = (start..end)
+                    .zip(&rendered)
+                    .map(|(i, lines)| {
+                        // Pad by the visible width, escape sequences take no space.
+                        let padding = board_width(&self.boards[i]) - (self.boards[i].columns() * (cell_width + 1) - 1);
+                        match lines.get(row) {
+                            Some(line) => format!("{}{}", line, " ".repeat(padding)),
+                            None => " ".repeat(board_width(&self.boards[i])),
+                        }
+                    })
+                    .collect();
+                out += line.join(gap).trim_end();
+                out += "\n";
+            }
+            out += "\n";
+            start = end;
+        }
+
+        out += "Scoreboard:\n";
+        let finished: Vec<usize> = self.game.ranking().into_iter().filter(|&i| self.win(i).is_some()).collect();
+        if finished.is_empty() {
+            out += "  no board has won yet\n";
+        }
+        for i in finished {
+            let win = self.win(i).unwrap();
+            out += &format!(
+                "  #{} board {} on turn {} with {} ({:?}), score {}\n",
+                win.rank,
+                self.positions[i],
+                win.turn,
+                win.number,
+                win.line,
+                win.score()
+            );
+        }
+
+        out
     }
+}
+
+/// Plays the game in `filename` on the terminal. The input is read line by
+/// line, so every draw waits for Enter instead of a single keypress.
+fn play_interactive(filename: &str) {
+    let rules = BingoRules::default();
+    let (numbers, boards) = match read_boards::<u8>(filename, &rules) {
+        Ok(input) => input,
+        Err(error) => {
+            println!("Cannot play {}: {:?}", filename, error);
+            return;
+        }
+    };
+
+    let mut player = BingoPlayer::new(numbers, boards, &rules);
+    let stdin = std::io::stdin();
+    loop {
+        print!("\x1b[2J\x1b[H{}", player.render(120));
+        if player.is_finished() {
+            break;
+        }
+        println!("Press Enter to draw the next number or q to quit.");
 
-    (last_number as u32) * score
+        let mut input = String::new();
+        if stdin.read_line(&mut input).unwrap_or(0) == 0 || input.trim() == "q" {
+            break;
+        }
+        player.step();
+    }
 }
 
-fn part1(numbers: &Vec<BingoNumber>, boards: &Vec<Board>) -> u32 {
-    play_bingo(numbers, boards, true)
+fn part1(numbers: &[u8], boards: &[Board]) -> u64 {
+    let game = play_bingo(numbers, boards, &BingoRules::default());
+    game.first_winner().map_or(0, |win| win.score())
 }
 
-fn part2(numbers: &Vec<BingoNumber>, boards: &Vec<Board>) -> u32 {
-    play_bingo(numbers, boards, false)
+fn part2(numbers: &[u8], boards: &[Board]) -> u64 {
+    let game = play_bingo(numbers, boards, &BingoRules::default());
+    game.last_winner().map_or(0, |win| win.score())
 }
 
 
+fn print_timeline<N: BingoNumber>(game: &BingoGame<N>) {
+    println!("rank  board   turn  number  line               unmarked     score");
+    for i in game.ranking() {
+        match &game.results[i] {
+            Some(win) => println!(
+                "{:>4} {:>6} {:>6} {:>7}  {:<16} {:>10} {:>9}",
+                win.rank,
+                i,
+                win.turn,
+                win.number,
+                format!("{:?}", win.line),
+                win.unmarked_sum,
+                win.score()
+            ),
+            None => println!("{:>4} {:>6}  never wins", "-", i),
+        }
+    }
+}
+
 fn main() {
+    // Pass "timeline" to print when and how every board wins,
+    // "odds [trials] [seed]" to estimate the odds of every board for random
+    // draw orders, or "first <board>" / "last <board> [node limit]" to search
+    // for the shortest draw order letting a board win first or last, or
+    // "play [file]" to step through a game interactively, one draw per Enter.
+    let args: Vec<String> = std::env::args().collect();
+    if args.get(1).map(|arg| arg.as_str()) == Some("play") {
+        play_interactive(args.get(2).map_or("inputs/04.txt", |s| s.as_str()));
+        return;
+    }
+
     let (numbers, boards) = read_input("inputs/04.txt");
+    match args.get(1).map(|arg| arg.as_str()) {
+        Some("timeline") => {
+            print_timeline(&play_bingo(&numbers, &boards, &BingoRules::default()));
+            return;
+        }
+        Some("odds") => {
+            let trials = args.get(2).map_or(10000, |s| s.parse().expect("Cannot parse number of trials"));
+            let seed = args.get(3).map_or(2021, |s| s.parse().expect("Cannot parse seed"));
+            let odds = estimate_win_odds(&numbers, &boards, &BingoRules::default(), trials, seed);
+            print_win_odds(&odds);
+            return;
+        }
+        Some(mode @ ("first" | "last")) => {
+            let target: usize = args
+                .get(2)
+                .expect("Missing board index")
+                .parse()
+                .expect("Cannot parse board index");
+            let rules = BingoRules::default();
+            let win_last = mode == "last";
+            let plan = if win_last {
+                let node_limit = args.get(3).map_or(1_000_000, |s| s.parse().expect("Cannot parse node limit"));
+                plan_last_win(&boards, &rules, target, node_limit)
+            } else {
+                plan_first_win(&boards, &rules, target)
+            };
+            match plan {
+                Ok(plan) => {
+                    let draws: Vec<String> = plan.draws.iter().map(|n| n.to_string()).collect();
+                    println!("{} draws{}: {}", draws.len(), if plan.optimal {""} else {" (not proven optimal)"}, draws.join(","));
+                    println!("Replay confirms plan: {}", check_plan(&plan, &boards, &rules, target, win_last));
+                }
+                Err(error) => println!("No draw order found: {:?}", error),
+            }
+            return;
+        }
+        _ => {}
+    }
+
     println!("Part 1: Product of board score and last number of first winning board: {}", part1(&numbers, &boards));
     println!("Part 2: Product of board score and last number of last winning board: {}", part2(&numbers, &boards));
 }
@@ -151,4 +1229,184 @@ mod tests04 {
         let product = part2(&numbers, &boards);
         assert_eq!(product, 1924);
     }
+
+    #[test]
+    fn test02() {
+        let rules = BingoRules { shape: None, diagonals: true };
+        let (numbers, boards) = read_input_with_rules::<u16>("test_inputs/04_02.txt", &rules).unwrap();
+        assert_eq!(boards.len(), 3);
+
+        // The first board wins on its anti-diagonal before any row or column is complete.
+        let game = play_bingo(&numbers, &boards, &rules);
+        assert_eq!(game.first_winner().unwrap().line, BingoLine::AntiDiagonal);
+        assert_eq!(game.first_winner().unwrap().score(), 700 * 3000);
+
+        let game = play_bingo(&numbers, &boards, &BingoRules::default());
+        assert_eq!(game.first_winner().unwrap().score(), 1000 * 10500);
+    }
+
+    #[test]
+    fn test03() {
+        let rules = BingoRules { shape: Some((5, 5)), diagonals: false };
+        let error = read_input_with_rules::<u16>("test_inputs/04_02.txt", &rules).unwrap_err();
+        assert_eq!(error, BoardError::InvalidDimensions { expected: (5, 5), actual: (3, 3) });
+
+        let rules = BingoRules { shape: Some((2, 4)), diagonals: false };
+        let (_, boards) = read_input_with_rules::<u8>("test_inputs/04_03.txt", &rules).unwrap();
+        assert_eq!(boards.len(), 2);
+        assert_eq!((boards[0].rows(), boards[0].columns()), (2, 4));
+    }
+
+    #[test]
+    fn test04() {
+        let (numbers, boards) = read_input("test_inputs/04_01.txt");
+        let game = play_bingo(&numbers, &boards, &BingoRules::default());
+
+        assert_eq!(game.ranking(), vec![2, 0, 1]);
+        assert_eq!(
+            game.results[2],
+            Some(Win { turn: 12, number: 24, line: BingoLine::Row(0), unmarked_sum: 188, rank: 1 })
+        );
+        assert_eq!(
+            game.results[1],
+            Some(Win { turn: 15, number: 13, line: BingoLine::Column(2), unmarked_sum: 148, rank: 3 })
+        );
+
+        // The second board of this file never wins.
+        let (numbers, boards) = read_input_with_rules::<u8>("test_inputs/04_03.txt", &BingoRules::default()).unwrap();
+        let game = play_bingo(&numbers, &boards, &BingoRules::default());
+        assert_eq!(game.ranking(), vec![0, 1]);
+        assert_eq!(game.results[0].as_ref().unwrap().line, BingoLine::Row(0));
+        assert_eq!(game.results[1], None);
+        assert_eq!(game.last_winner().unwrap().rank, 1);
+    }
+
+    #[test]
+    fn test05() {
+        let (numbers, boards) = read_input("inputs/04.txt");
+        for diagonals in [false, true] {
+            let rules = BingoRules { shape: None, diagonals };
+            let game = play_bingo(&numbers, &boards, &rules);
+            let reference = play_bingo_naive(&numbers, &boards, &rules);
+            assert_eq!(game.results, reference.results);
+
+            for (board, win) in boards.iter().zip(&game.results) {
+                let win = win.as_ref().unwrap();
+                let drawn: HashSet<u8> = numbers[..win.turn].iter().cloned().collect();
+                assert!(board_wins(board, &drawn, &rules));
+                let before: HashSet<u8> = numbers[..win.turn - 1].iter().cloned().collect();
+                assert!(!board_wins(board, &before, &rules));
+            }
+        }
+    }
+
+    #[test]
+    fn test06() {
+        let (numbers, boards) = read_input("test_inputs/04_01.txt");
+        let rules = BingoRules::default();
+
+        let odds = estimate_win_odds(&numbers, &boards, &rules, 2000, 42);
+        assert_eq!(odds, estimate_win_odds(&numbers, &boards, &rules, 2000, 42));
+        assert_ne!(odds, estimate_win_odds(&numbers, &boards, &rules, 2000, 43));
+
+        // Every number of these boards is drawn, so some board always wins first and last.
+        let first: f64 = odds.iter().map(|o| o.first.value).sum();
+        let last: f64 = odds.iter().map(|o| o.last.value).sum();
+        assert!((first - 1.0).abs() < 1e-9);
+        assert!((last - 1.0).abs() < 1e-9);
+
+        for board_odds in &odds {
+            assert_eq!(board_odds.wins, 2000);
+            assert!(board_odds.first.low <= board_odds.first.value && board_odds.first.value <= board_odds.first.high);
+            let turn = board_odds.expected_turn.unwrap();
+            assert!(turn.value >= 5.0 && turn.value <= numbers.len() as f64);
+        }
+    }
+
+    #[test]
+    fn test07() {
+        let (_, boards) = read_input("test_inputs/04_01.txt");
+        let rules = BingoRules::default();
+
+        for target in 0..boards.len() {
+            let plan = plan_first_win(&boards, &rules, target).unwrap();
+            assert_eq!(plan.draws.len(), 5);
+            assert!(check_plan(&plan, &boards, &rules, target, false));
+
+            let plan = plan_last_win(&boards, &rules, target, 100_000).unwrap();
+            assert!(plan.optimal);
+            assert!(check_plan(&plan, &boards, &rules, target, true));
+        }
+
+        let (numbers, boards) = read_input("inputs/04.txt");
+        let plan = plan_first_win(&boards, &rules, 17).unwrap();
+        assert!(check_plan(&plan, &boards, &rules, 17, false));
+        assert!(plan.draws.len() < play_bingo(&numbers, &boards, &rules).results[17].as_ref().unwrap().turn);
+    }
+
+    #[test]
+    fn test08() {
+        let rules = BingoRules::default();
+        let board = || Board::new(vec![vec![1u8, 2], vec![3, 4]], (2, 2)).unwrap();
+        let boards = vec![board(), board(), Board::new(vec![vec![1, 5], vec![6, 7]], (2, 2)).unwrap()];
+
+        // Identical boards always finish together and rank by position.
+        assert_eq!(plan_first_win(&boards, &rules, 0).unwrap().draws.len(), 2);
+        assert_eq!(plan_first_win(&boards, &rules, 1).unwrap_err(), PlanError::Impossible { blockers: vec![0] });
+        assert_eq!(plan_last_win(&boards, &rules, 0, 1000).unwrap_err(), PlanError::Impossible { blockers: vec![1] });
+
+        let plan = plan_last_win(&boards, &rules, 2, 1000).unwrap();
+        assert_eq!(plan.draws, vec![1, 2, 5]);
+        assert!(check_plan(&plan, &boards, &rules, 2, true));
+
+        // Board 0 ranks ahead of board 1 when both finish on the last draw.
+        let tie = DrawPlan { draws: vec![1, 5, 2], optimal: true };
+        assert!(check_plan(&tie, &boards, &rules, 1, true));
+        let plan = plan_last_win(&boards, &rules, 1, 1000).unwrap();
+        assert_eq!(plan.draws.len(), 3);
+        assert!(plan.optimal);
+        assert!(check_plan(&plan, &boards, &rules, 1, true));
+    }
+
+    #[test]
+    fn test09() {
+        let rules = BingoRules::default();
+        let (numbers, boards) = read_boards::<u8>("test_inputs/04_04.txt", &rules).unwrap();
+        assert_eq!(boards.len(), 4);
+        assert_eq!(
+            boards[1].as_ref().unwrap_err(),
+            &BoardError::InvalidDimensions { expected: (3, 3), actual: (3, 4) }
+        );
+        assert_eq!(
+            boards[2].as_ref().unwrap_err(),
+            &BoardError::InvalidNumber { line: 12, value: "x7".to_string() }
+        );
+
+        let mut player = BingoPlayer::new(numbers, boards, &rules);
+        assert_eq!(player.positions, vec![0, 3]);
+        assert_eq!(player.problems.len(), 3);
+        assert!(player.problems[2].starts_with("Board 3 can never win"));
+        assert!(player.render(80).contains("no board has won yet"));
+
+        let mut steps = 0;
+        while player.step() {
+            steps += 1;
+        }
+        assert_eq!(steps, 4);
+
+        let rendered = player.render(80);
+        assert!(rendered.contains("Board 0 #1"));
+        assert!(rendered.contains("#1 board 0 on turn 4 with 3 (Row(0)), score 225"));
+        assert!(rendered.contains(&format!("{}{:>2}{}", STYLE_WINNING_LINE, 3, STYLE_RESET)));
+        assert!(rendered.contains(&format!("{}{:>2}{}", STYLE_MARKED, 21, STYLE_RESET)));
+    }
+
+    #[test]
+    fn test10() {
+        let rules = BingoRules::default();
+        assert!(matches!(
+            read_boards::<u8>("test_inputs/04_missing.txt", &rules),
+            Err(BoardError::Unreadable(_))
+        ));
+    }
 }
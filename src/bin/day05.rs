@@ -1,10 +1,10 @@
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap, HashSet};
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::iter::Iterator;
 use std::str::FromStr;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 struct Point {
     x: i32,
     y: i32,
@@ -28,7 +28,7 @@ impl FromStr for Point {
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 struct Line {
     start: Point,
     end: Point,
@@ -36,7 +36,34 @@ struct Line {
 
 impl Line {
     fn points(&self) -> LinePoints {
-        LinePoints::new(self)
+        self.rasterize(Rasterization::Bresenham)
+    }
+
+    fn rasterize(&self, mode: Rasterization) -> LinePoints {
+        LinePoints::new(self, mode)
+    }
+
+    /// Test whether a line is horizontal, vertical or diagonal (i.e. 45°).
+    fn is_straight(&self) -> bool {
+        let dx = (self.end.x as i64 - self.start.x as i64).abs();
+        let dy = (self.end.y as i64 - self.start.y as i64).abs();
+        dx == 0 || dy == 0 || dx == dy
+    }
+
+    fn contains(&self, point: &Point, mode: Rasterization) -> bool {
+        let (x_min, x_max) = (self.start.x.min(self.end.x), self.start.x.max(self.end.x));
+        let (y_min, y_max) = (self.start.y.min(self.end.y), self.start.y.max(self.end.y));
+        if point.x < x_min || point.x > x_max || point.y < y_min || point.y > y_max {
+            return false;
+        }
+
+        if self.is_straight() {
+            // Both rasterizations give exactly the cells on the line.
+            let (dx, dy) = ((self.end.x - self.start.x) as i64, (self.end.y - self.start.y) as i64);
+            (point.x - self.start.x) as i64 * dy == (point.y - self.start.y) as i64 * dx
+        } else {
+            self.rasterize(mode).any(|p| p == *point)
+        }
     }
 }
 
@@ -52,23 +79,56 @@ impl FromStr for Line {
     }
 }
 
+/// How a line is turned into grid points. Both agree for horizontal,
+/// vertical and diagonal (i.e. 45°) lines.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Rasterization {
+    /// One point per step along the longer axis, as chosen by Bresenham's algorithm.
+    Bresenham,
+    /// Only the points with integer coordinates lying exactly on the line.
+    LatticePoints,
+}
+
+fn gcd(a: i64, b: i64) -> i64 {
+    if b == 0 { a.abs() } else { gcd(b, a % b) }
+}
+
 struct LinePoints {
-    current_x: i32,
-    current_y: i32,
-    last_x: i32,
-    last_y: i32,
-    last: bool,
+    current_x: i64,
+    current_y: i64,
+    last_x: i64,
+    last_y: i64,
+    mode: Rasterization,
+    // Bresenham state
+    dx: i64,
+    dy: i64,
+    err: i64,
+    // Lattice step
+    step_x: i64,
+    step_y: i64,
     finished: bool,
 }
 
 impl LinePoints {
-    fn new(line: &Line) -> LinePoints {
+    fn new(line: &Line, mode: Rasterization) -> LinePoints {
+        let (x0, y0) = (line.start.x as i64, line.start.y as i64);
+        let (x1, y1) = (line.end.x as i64, line.end.y as i64);
+
+        let dx = (x1 - x0).abs();
+        let dy = -(y1 - y0).abs();
+        let g = gcd(x1 - x0, y1 - y0).max(1);
+
         LinePoints {
-            current_x: line.start.x,
-            current_y: line.start.y,
-            last_x: line.end.x,
-            last_y: line.end.y,
-            last: false,
+            current_x: x0,
+            current_y: y0,
+            last_x: x1,
+            last_y: y1,
+            mode,
+            dx,
+            dy,
+            err: dx + dy,
+            step_x: (x1 - x0) / g,
+            step_y: (y1 - y0) / g,
             finished: false,
         }
     }
@@ -78,30 +138,39 @@ impl Iterator for LinePoints {
     type Item = Point;
 
     fn next(&mut self) -> Option<Self::Item> {
-        // This is only correct for straight horizontal, vertical and diagonal (i.e. 45°) lines.
         if self.finished {
-            None
+            return None;
         }
-        else if self.last {
-            self.finished = true;
-            Some(Point{x: self.current_x, y: self.current_y})
-        }
-        else {
-            let x_dir = (self.last_x - self.current_x).signum();
-            let y_dir = (self.last_y - self.current_y).signum();
 
-            let point = Point {
-                x: self.current_x,
-                y: self.current_y,
-            };
+        let point = Point {
+            x: self.current_x as i32,
+            y: self.current_y as i32,
+        };
 
-            self.current_x += x_dir;
-            self.current_y += y_dir;
-
-            self.last = self.current_x == self.last_x && self.current_y == self.last_y;
+        if self.current_x == self.last_x && self.current_y == self.last_y {
+            self.finished = true;
+            return Some(point);
+        }
 
-            Some(point)
+        match self.mode {
+            Rasterization::Bresenham => {
+                let e2 = 2 * self.err;
+                if e2 >= self.dy {
+                    self.err += self.dy;
+                    self.current_x += (self.last_x - self.current_x).signum();
+                }
+                if e2 <= self.dx {
+                    self.err += self.dx;
+                    self.current_y += (self.last_y - self.current_y).signum();
+                }
+            }
+            Rasterization::LatticePoints => {
+                self.current_x += self.step_x;
+                self.current_y += self.step_y;
+            }
         }
+
+        Some(point)
     }
 }
 
@@ -147,11 +216,14 @@ fn print_map(map: &HashMap<(i32, i32), u32>) {
     }
 }
 
-fn count_overlaps(lines: &Vec<&Line>) -> u32 {
+fn count_overlaps<F>(lines: &[&Line], points: F) -> u32
+where
+    F: Fn(&Line) -> LinePoints,
+{
     let mut map: HashMap<(i32, i32), u32> = HashMap::new();
 
     for line in lines {
-        for point in line.points() {
+        for point in points(line) {
             let (x, y) = point.as_tuple();
             // println!("{:?}: {:?}", (x, y), map.get(&(x, y)));
             
@@ -171,21 +243,405 @@ fn count_overlaps(lines: &Vec<&Line>) -> u32 {
         .count() as u32
 }
 
-fn part1(lines: &Vec<Line>) -> u32 {
+/// A vertical or diagonal line seen by a sweep over the rows. On row `y` it
+/// covers the single cell `x = offset + slope * y`.
+#[derive(Debug, Clone, Copy)]
+struct SweepLine {
+    slope: i64,
+    offset: i64,
+    y_min: i64,
+    y_max: i64,
+}
+
+impl SweepLine {
+    fn x(&self, y: i64) -> i64 {
+        self.offset + self.slope * y
+    }
+}
+
+/// Count the cells covered by two or more lines using a sweep over the rows.
+/// Only horizontal, vertical and diagonal lines are supported; for any other
+/// line `None` is returned. Memory depends on the number of lines only.
+///
+/// Rows are grouped into bands in which the same vertical and diagonal lines
+/// are active and which contain no horizontal line. Within a band, collinear
+/// lines overlap on every row and non-parallel lines meet in at most one cell,
+/// so each band is counted from pairs of lines instead of cell by cell. Rows
+/// with horizontal lines are counted one at a time with an interval sweep.
+fn count_overlaps_sweep(lines: &[&Line]) -> Option<u64> {
+    let mut horizontals: Vec<(i64, i64, i64)> = Vec::new();
+    let mut sweep_lines: Vec<SweepLine> = Vec::new();
+
+    for line in lines {
+        let (x0, y0) = (line.start.x as i64, line.start.y as i64);
+        let (x1, y1) = (line.end.x as i64, line.end.y as i64);
+        let (dx, dy) = (x1 - x0, y1 - y0);
+
+        if dy == 0 {
+            horizontals.push((y0, x0.min(x1), x0.max(x1)));
+        } else if dx == 0 || dx.abs() == dy.abs() {
+            let slope = dx / dy.abs() * dy.signum();
+            sweep_lines.push(SweepLine {
+                slope,
+                offset: x0 - slope * y0,
+                y_min: y0.min(y1),
+                y_max: y0.max(y1),
+            });
+        } else {
+            return None;
+        }
+    }
+
+    // Rows at which the set of active lines changes, and the rows with
+    // horizontal lines which are handled on their own.
+    let mut breaks: Vec<i64> = Vec::with_capacity(2 * lines.len());
+    for line in &sweep_lines {
+        breaks.push(line.y_min);
+        breaks.push(line.y_max + 1);
+    }
+    horizontals.sort_unstable();
+    let mut horizontal_rows: Vec<i64> = horizontals.iter().map(|&(y, _, _)| y).collect();
+    horizontal_rows.dedup();
+    for &y in &horizontal_rows {
+        breaks.push(y);
+        breaks.push(y + 1);
+    }
+    breaks.sort_unstable();
+    breaks.dedup();
+
+    // Every line starts and ends on a break, so the active lines only change
+    // from one band to the next and never within one.
+    sweep_lines.sort_unstable_by_key(|line| line.y_min);
+    let mut pending = sweep_lines.iter().peekable();
+    let mut active: Vec<&SweepLine> = Vec::new();
+
+    let mut count = 0;
+    for band in breaks.windows(2) {
+        let (y_start, y_end) = (band[0], band[1] - 1);
+        active.retain(|line| y_start <= line.y_max);
+        while let Some(line) = pending.next_if(|line| line.y_min <= y_start) {
+            active.push(line);
+        }
+
+        if horizontal_rows.binary_search(&y_start).is_ok() {
+            let start = horizontals.partition_point(|&(y, _, _)| y < y_start);
+            let end = horizontals.partition_point(|&(y, _, _)| y <= y_start);
+            let mut intervals: Vec<(i64, i64)> = horizontals[start..end].iter().map(|&(_, a, b)| (a, b)).collect();
+            intervals.extend(active.iter().map(|line| (line.x(y_start), line.x(y_start))));
+            count += count_row_overlaps(&intervals);
+        } else if active.len() > 1 {
+            count += count_band_overlaps(&active, y_start, y_end);
+        }
+    }
+
+    Some(count)
+}
+
+/// Number of integer positions covered by at least two of the closed intervals.
+fn count_row_overlaps(intervals: &[(i64, i64)]) -> u64 {
+    let mut events: Vec<(i64, i32)> = Vec::with_capacity(2 * intervals.len());
+    for &(a, b) in intervals {
+        events.push((a, 1));
+        events.push((b + 1, -1));
+    }
+    events.sort_unstable();
+
+    let mut count = 0;
+    let mut coverage = 0;
+    let mut last = 0;
+    for (x, change) in events {
+        if coverage > 1 {
+            count += (x - last) as u64;
+        }
+        coverage += change;
+        last = x;
+    }
+    count
+}
+
+/// Number of cells covered by at least two of the lines, all of which are
+/// active on every row from `y_start` to `y_end`.
+fn count_band_overlaps(active: &[&SweepLine], y_start: i64, y_end: i64) -> u64 {
+    // Collinear lines form a group, which overlaps itself on every row if it
+    // has more than one member.
+    let mut groups: HashMap<(i64, i64), u32> = HashMap::new();
+    for line in active {
+        *groups.entry((line.slope, line.offset)).or_insert(0) += 1;
+    }
+    let overlapping_groups = groups.values().filter(|&&n| n > 1).count() as u64;
+    let mut count = overlapping_groups * (y_end - y_start + 1) as u64;
+
+    // Cells where groups of different slopes meet. At most three groups, one
+    // per slope, meet in a cell; such a cell is only handled for the pair of
+    // the two smallest slopes. If none of the groups overlaps itself, the cell
+    // is new. Otherwise it was counted once per overlapping group above.
+    let keys: Vec<(i64, i64)> = groups.keys().cloned().collect();
+    for (i, &(s1, c1)) in keys.iter().enumerate() {
+        for &(s2, c2) in &keys[i + 1..] {
+            if s1 == s2 || (c2 - c1) % (s1 - s2) != 0 {
+                continue;
+            }
+            let y = (c2 - c1) / (s1 - s2);
+            if y < y_start || y > y_end {
+                continue;
+            }
+            let x = c1 + s1 * y;
+
+            let meeting: Vec<(i64, i64)> = [-1, 0, 1]
+                .iter()
+                .map(|&s| (s, x - s * y))
+                .filter(|key| groups.contains_key(key))
+                .collect();
+            if meeting.len() == 3 && (s1.min(s2), s1.max(s2)) != (-1, 0) {
+                continue;
+            }
+            match meeting.iter().filter(|key| groups[key] > 1).count() {
+                0 => count += 1,
+                overlapping => count -= overlapping as u64 - 1,
+            }
+        }
+    }
+
+    count
+}
+
+/// Axis-aligned rectangle including its borders.
+#[derive(Debug, Clone, Copy)]
+struct Rect {
+    min: Point,
+    max: Point,
+}
+
+impl Rect {
+    fn new(a: Point, b: Point) -> Rect {
+        Rect {
+            min: Point { x: a.x.min(b.x), y: a.y.min(b.y) },
+            max: Point { x: a.x.max(b.x), y: a.y.max(b.y) },
+        }
+    }
+
+    fn contains(&self, point: &Point) -> bool {
+        self.min.x <= point.x && point.x <= self.max.x && self.min.y <= point.y && point.y <= self.max.y
+    }
+
+    fn area(&self) -> u64 {
+        (self.max.x - self.min.x + 1) as u64 * (self.max.y - self.min.y + 1) as u64
+    }
+}
+
+/// Vent lines indexed by a grid of square buckets. Every line is stored in
+/// the buckets it passes through, so queries only look at nearby lines.
+struct VentField {
+    lines: Vec<Line>,
+    mode: Rasterization,
+    bucket_size: i64,
+    buckets: HashMap<(i64, i64), Vec<usize>>,
+    /// Smallest and largest bucket coordinates in use.
+    bounds: ((i64, i64), (i64, i64)),
+}
+
+impl VentField {
+    /// Number of buckets along the longer side of the field.
+    const BUCKETS: i64 = 128;
+
+    fn new(lines: Vec<Line>, mode: Rasterization) -> VentField {
+        let extent = lines
+            .iter()
+            .flat_map(|line| [line.start, line.end])
+            .map(|p| p.x.unsigned_abs().max(p.y.unsigned_abs()))
+            .max()
+            .unwrap_or(0);
+        let bucket_size = (extent as i64 / Self::BUCKETS).max(1);
+
+        let mut field = VentField { lines, mode, bucket_size, buckets: HashMap::new(), bounds: ((0, 0), (-1, -1)) };
+        let mut bounds: Option<((i64, i64), (i64, i64))> = None;
+        for i in 0..field.lines.len() {
+            for (bx, by) in field.line_buckets(&field.lines[i]) {
+                field.buckets.entry((bx, by)).or_default().push(i);
+                let ((x_min, y_min), (x_max, y_max)) = bounds.unwrap_or(((bx, by), (bx, by)));
+                bounds = Some(((x_min.min(bx), y_min.min(by)), (x_max.max(bx), y_max.max(by))));
+            }
+        }
+        if let Some(bounds) = bounds {
+            field.bounds = bounds;
+        }
+        field
+    }
+
+    fn bucket_index(&self, coordinate: i64) -> i64 {
+        coordinate.div_euclid(self.bucket_size)
+    }
+
+    fn bucket(&self, point: &Point) -> (i64, i64) {
+        (self.bucket_index(point.x as i64), self.bucket_index(point.y as i64))
+    }
+
+    /// Buckets the line passes through. On a shallow line the rasterized
+    /// cells of one row form a run about `|dx| / |dy|` cells long around the
+    /// exact line, so every row of buckets gets the range of x the line spans
+    /// there widened by half that run and one more cell.
+    fn line_buckets(&self, line: &Line) -> Vec<(i64, i64)> {
+        let (x0, y0) = (line.start.x as i64, line.start.y as i64);
+        let (x1, y1) = (line.end.x as i64, line.end.y as i64);
+        let by_min = self.bucket_index(y0.min(y1));
+        let by_max = self.bucket_index(y0.max(y1));
+        let size = self.bucket_size;
+
+        let mut buckets = Vec::new();
+        for by in by_min..=by_max {
+            let (x_min, x_max) = if y0 == y1 {
+                (x0.min(x1), x0.max(x1))
+            } else {
+                let y_start = (by * size).max(y0.min(y1));
+                let y_end = ((by + 1) * size - 1).min(y0.max(y1));
+                let xa = x0 + (x1 - x0) * (y_start - y0) / (y1 - y0);
+                let xb = x0 + (x1 - x0) * (y_end - y0) / (y1 - y0);
+                let margin = ((x1 - x0).abs() + (y1 - y0).abs() - 1) / (y1 - y0).abs() / 2 + 1;
+                (xa.min(xb) - margin, xa.max(xb) + margin)
+            };
+            for bx in self.bucket_index(x_min)..=self.bucket_index(x_max) {
+                buckets.push((bx, by));
+            }
+        }
+        buckets
+    }
+
+    /// Indices of all lines passing through `point`.
+    fn lines_through(&self, point: &Point) -> Vec<usize> {
+        let mut lines: Vec<usize> = match self.buckets.get(&self.bucket(point)) {
+            Some(candidates) => candidates
+                .iter()
+                .cloned()
+                .filter(|&i| self.lines[i].contains(point, self.mode))
+                .collect(),
+            None => Vec::new(),
+        };
+        lines.sort_unstable();
+        lines
+    }
+
+    fn shared_cells(&self, a: &Line, b: &Line) -> Vec<Point> {
+        if b.is_straight() {
+            a.rasterize(self.mode).filter(|p| b.contains(p, self.mode)).collect()
+        } else {
+            let cells: HashSet<Point> = b.rasterize(self.mode).collect();
+            a.rasterize(self.mode).filter(|p| cells.contains(p)).collect()
+        }
+    }
+
+    /// All pairs of lines sharing at least one cell together with those cells.
+    fn overlapping_pairs(&self) -> Vec<(usize, usize, Vec<Point>)> {
+        let mut candidates: HashSet<(usize, usize)> = HashSet::new();
+        for lines in self.buckets.values() {
+            for (k, &i) in lines.iter().enumerate() {
+                for &j in &lines[k + 1..] {
+                    candidates.insert((i.min(j), i.max(j)));
+                }
+            }
+        }
+
+        let mut pairs: Vec<(usize, usize, Vec<Point>)> = candidates
+            .into_iter()
+            .map(|(i, j)| (i, j, self.shared_cells(&self.lines[i], &self.lines[j])))
+            .filter(|(_, _, cells)| !cells.is_empty())
+            .collect();
+        pairs.sort_unstable_by_key(|&(i, j, _)| (i, j));
+        pairs
+    }
+
+    /// Number of cells inside `rect` for every number of lines covering them.
+    fn coverage_histogram(&self, rect: &Rect) -> BTreeMap<u32, u64> {
+        // Only the buckets both inside the rectangle and in use are visited.
+        let ((x_low, y_low), (x_high, y_high)) = self.bounds;
+        let (bx_min, by_min) = self.bucket(&rect.min);
+        let (bx_max, by_max) = self.bucket(&rect.max);
+        let mut candidates: HashSet<usize> = HashSet::new();
+        for by in by_min.max(y_low)..=by_max.min(y_high) {
+            for bx in bx_min.max(x_low)..=bx_max.min(x_high) {
+                if let Some(lines) = self.buckets.get(&(bx, by)) {
+                    candidates.extend(lines);
+                }
+            }
+        }
+
+        let mut coverage: HashMap<Point, u32> = HashMap::new();
+        for i in candidates {
+            for point in self.lines[i].rasterize(self.mode).filter(|p| rect.contains(p)) {
+                *coverage.entry(point).or_insert(0) += 1;
+            }
+        }
+
+        let mut histogram: BTreeMap<u32, u64> = BTreeMap::new();
+        let uncovered = rect.area() - coverage.len() as u64;
+        if uncovered > 0 {
+            histogram.insert(0, uncovered);
+        }
+        for count in coverage.into_values() {
+            *histogram.entry(count).or_insert(0) += 1;
+        }
+        histogram
+    }
+}
+
+fn part1(lines: &[Line]) -> u64 {
     // Only consider vertical or horizontal lines.
     let lines: Vec<&Line> = lines.iter().filter(|&l| is_hvline(l)).collect();
 
-    count_overlaps(&lines)
+    count_overlaps_sweep(&lines).unwrap()
 }
 
-fn part2(lines: &Vec<Line>) -> u32 {
-    // Consider all lines
+fn part2(lines: &[Line]) -> u64 {
+    // Consider all lines. Lines of other slopes than 45° need to be rasterized.
     let lines: Vec<&Line> = lines.iter().collect();
-    count_overlaps(&lines)
+    count_overlaps_sweep(&lines)
+        .unwrap_or_else(|| count_overlaps(&lines, Line::points) as u64)
+}
+
+fn parse_point(s: &str) -> Point {
+    Point::from_str(s).unwrap_or_else(|_| panic!("Cannot parse point {:?}, expected x,y", s))
 }
 
 fn main() {
-    let input = read_input("inputs/05.txt");
+    // Subcommands query the vent field:
+    //   at <x,y> [file]                 lines passing through a point
+    //   overlaps [file]                 pairs of lines sharing cells
+    //   histogram <x,y> <x,y> [file]    coverage histogram inside a rectangle
+    // Otherwise, optionally pass another vent file and "--lattice" to
+    // additionally count only the points lying exactly on the lines.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    let file_arg = |i: usize| args.get(i).map_or("inputs/05.txt", |arg| arg.as_str());
+
+    match args.first().map(|arg| arg.as_str()) {
+        Some("at") => {
+            let point = parse_point(args.get(1).expect("Missing point"));
+            let field = VentField::new(read_input(file_arg(2)), Rasterization::Bresenham);
+            for i in field.lines_through(&point) {
+                println!("{}: {:?}", i, field.lines[i]);
+            }
+            return;
+        }
+        Some("overlaps") => {
+            let field = VentField::new(read_input(file_arg(1)), Rasterization::Bresenham);
+            for (i, j, cells) in field.overlapping_pairs() {
+                let cells: Vec<String> = cells.iter().map(|p| format!("{},{}", p.x, p.y)).collect();
+                println!("{} and {}: {}", i, j, cells.join(" "));
+            }
+            return;
+        }
+        Some("histogram") => {
+            let a = parse_point(args.get(1).expect("Missing first corner"));
+            let b = parse_point(args.get(2).expect("Missing second corner"));
+            let field = VentField::new(read_input(file_arg(3)), Rasterization::Bresenham);
+            for (lines, cells) in field.coverage_histogram(&Rect::new(a, b)) {
+                println!("{} lines: {} cells", lines, cells);
+            }
+            return;
+        }
+        _ => {}
+    }
+
+    let filename = args.iter().find(|arg| !arg.starts_with("--")).map_or("inputs/05.txt", |arg| arg.as_str());
+    let input = read_input(filename);
     // let input = read_input("test_inputs/05_01.txt");
     // println!("{:?}", input);
 
@@ -200,6 +656,12 @@ fn main() {
         "Part 1: Number of tiles with more than with a cloud density higher than one: {}",
         solution
     );
+
+    if args.iter().any(|arg| arg == "--lattice") {
+        let lines: Vec<&Line> = input.iter().collect();
+        let solution = count_overlaps(&lines, |line| line.rasterize(Rasterization::LatticePoints));
+        println!("Number of lattice points covered by more than one line: {}", solution);
+    }
 }
 
 #[cfg(test)]
@@ -221,4 +683,161 @@ mod tests05 {
 
         assert_eq!(solution, 12);
     }
+
+    #[test]
+    fn test03() {
+        let line = Line::from_str("0,0 -> 6,4").unwrap();
+
+        let points: Vec<(i32, i32)> = line.points().map(|p| p.as_tuple()).collect();
+        assert_eq!(points, vec![(0, 0), (1, 1), (2, 1), (3, 2), (4, 3), (5, 3), (6, 4)]);
+
+        let points: Vec<(i32, i32)> = line.rasterize(Rasterization::LatticePoints).map(|p| p.as_tuple()).collect();
+        assert_eq!(points, vec![(0, 0), (3, 2), (6, 4)]);
+
+        let line = Line::from_str("5,9 -> 3,0").unwrap();
+        let points: Vec<(i32, i32)> = line.points().map(|p| p.as_tuple()).collect();
+        assert_eq!(points.len(), 10);
+        assert_eq!(points.first(), Some(&(5, 9)));
+        assert_eq!(points.last(), Some(&(3, 0)));
+        assert_eq!(line.rasterize(Rasterization::LatticePoints).count(), 2);
+    }
+
+    #[test]
+    fn test04() {
+        // Both rasterizations agree with the old walk for straight and diagonal lines.
+        for line in read_input("test_inputs/05_01.txt") {
+            let bresenham: Vec<(i32, i32)> = line.points().map(|p| p.as_tuple()).collect();
+            let lattice: Vec<(i32, i32)> = line.rasterize(Rasterization::LatticePoints).map(|p| p.as_tuple()).collect();
+            let length = (line.end.x - line.start.x).abs().max((line.end.y - line.start.y).abs());
+            assert_eq!(bresenham, lattice);
+            assert_eq!(bresenham.len() as i32, length + 1);
+        }
+    }
+
+    #[test]
+    fn test05() {
+        let input = read_input("inputs/05.txt");
+        let all: Vec<&Line> = input.iter().collect();
+        let hv: Vec<&Line> = input.iter().filter(|&l| is_hvline(l)).collect();
+
+        assert_eq!(count_overlaps_sweep(&hv), Some(count_overlaps(&hv, Line::points) as u64));
+        assert_eq!(count_overlaps_sweep(&all), Some(count_overlaps(&all, Line::points) as u64));
+        assert_eq!(part2(&input), 21373);
+
+        let skewed = [Line::from_str("0,0 -> 4,2").unwrap()];
+        assert_eq!(count_overlaps_sweep(&skewed.iter().collect::<Vec<&Line>>()), None);
+    }
+
+    #[test]
+    fn test06() {
+        // Many short lines on a small field, so that collinear overlaps and
+        // crossings of three lines in one cell are common.
+        let mut state: u64 = 5;
+        let mut random = |n: i32| {
+            state = state.wrapping_mul(6364136223846793005).wrapping_add(1442695040888963407);
+            ((state >> 33) % n as u64) as i32
+        };
+
+        for _round in 0..50 {
+            let mut input = Vec::new();
+            for _line in 0..100 {
+                let (x, y) = (random(60), random(60));
+                let length = random(30);
+                let (dx, dy) = [(1, 0), (0, 1), (1, 1), (1, -1), (-1, 0), (0, 0)][random(6) as usize];
+                let end = Point { x: x + dx * length, y: y + dy * length };
+                input.push(Line { start: Point { x, y }, end });
+            }
+            let lines: Vec<&Line> = input.iter().collect();
+
+            assert_eq!(count_overlaps_sweep(&lines), Some(count_overlaps(&lines, Line::points) as u64));
+        }
+    }
+
+    #[test]
+    fn test07() {
+        let field = VentField::new(read_input("test_inputs/05_01.txt"), Rasterization::Bresenham);
+
+        assert_eq!(field.lines_through(&Point { x: 0, y: 9 }), vec![0, 6]);
+        assert_eq!(field.lines_through(&Point { x: 7, y: 4 }), vec![2, 4]);
+        assert_eq!(field.lines_through(&Point { x: 9, y: 9 }), vec![]);
+
+        let pairs = field.overlapping_pairs();
+        assert!(pairs.contains(&(0, 6, vec![Point { x: 0, y: 9 }, Point { x: 1, y: 9 }, Point { x: 2, y: 9 }])));
+        let cells: HashSet<Point> = pairs.into_iter().flat_map(|(_, _, cells)| cells).collect();
+        assert_eq!(cells.len(), 12);
+
+        let histogram = field.coverage_histogram(&Rect::new(Point { x: 0, y: 0 }, Point { x: 9, y: 9 }));
+        assert_eq!(histogram.get(&2), Some(&10));
+        assert_eq!(histogram.get(&3), Some(&2));
+        assert_eq!(histogram.values().sum::<u64>(), 100);
+
+        let histogram = field.coverage_histogram(&Rect::new(Point { x: 2, y: 9 }, Point { x: 0, y: 9 }));
+        assert_eq!(histogram, BTreeMap::from([(2, 3)]));
+    }
+
+    #[test]
+    fn test08() {
+        // Buckets are far smaller than the lines here.
+        let input = read_input("inputs/05.txt");
+        let all: Vec<&Line> = input.iter().collect();
+        let field = VentField::new(input.clone(), Rasterization::Bresenham);
+        assert!(field.bucket_size < 10);
+
+        let cells: HashSet<Point> = field.overlapping_pairs().into_iter().flat_map(|(_, _, cells)| cells).collect();
+        assert_eq!(cells.len() as u64, count_overlaps_sweep(&all).unwrap());
+
+        for line in &input {
+            for point in line.points().step_by(17) {
+                assert!(field.lines_through(&point).iter().any(|&i| field.lines[i].start == line.start));
+            }
+        }
+    }
+
+    #[test]
+    fn test09() {
+        // Shallow and steep lines, whose rows or columns span many cells.
+        let input: Vec<Line> = ["0,0 -> 100,1", "0,1 -> 100,0", "3,0 -> 5,90", "0,2 -> 1000,9", "50,0 -> 50,5"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let field = VentField::new(input.clone(), Rasterization::Bresenham);
+        assert_eq!(field.lines_through(&Point { x: 30, y: 0 }), vec![0]);
+
+        let mut coverage: HashMap<Point, u32> = HashMap::new();
+        for (i, line) in input.iter().enumerate() {
+            for point in line.points() {
+                assert!(field.lines_through(&point).contains(&i));
+                *coverage.entry(point).or_insert(0) += 1;
+            }
+        }
+
+        let cells: HashSet<Point> = field.overlapping_pairs().into_iter().flat_map(|(_, _, cells)| cells).collect();
+        assert_eq!(cells.len(), coverage.values().filter(|&&n| n > 1).count());
+        let histogram = field.coverage_histogram(&Rect::new(Point { x: 0, y: 0 }, Point { x: 1000, y: 90 }));
+        assert_eq!(histogram.get(&2).cloned(), Some(coverage.values().filter(|&&n| n == 2).count() as u64));
+    }
+
+    #[test]
+    fn test10() {
+        let line: Line = "0,0 -> 2000000,2000000".parse().unwrap();
+        assert!(line.contains(&Point { x: 1000000, y: 1000000 }, Rasterization::Bresenham));
+        assert!(!line.contains(&Point { x: 1000000, y: 1000001 }, Rasterization::Bresenham));
+
+        let field = VentField::new(vec![line, "2000000,0 -> 0,2000000".parse().unwrap()], Rasterization::Bresenham);
+        assert_eq!(field.lines_through(&Point { x: 1000000, y: 1000000 }), vec![0, 1]);
+        assert_eq!(field.lines_through(&Point { x: 1000000, y: 999999 }), vec![]);
+
+        // Coordinates down to i32::MIN still give a positive bucket size.
+        let input: Vec<Line> = ["-2147483648,0 -> -2147483648,5", "-2147483648,3 -> -2147483000,3"]
+            .iter()
+            .map(|s| s.parse().unwrap())
+            .collect();
+        let field = VentField::new(input, Rasterization::Bresenham);
+        assert_eq!(field.bucket_size, 1 << 24);
+        assert_eq!(field.lines_through(&Point { x: -2147483648, y: 3 }), vec![0, 1]);
+        let rect = Rect::new(Point { x: -2147483648, y: 0 }, Point { x: -2147483647, y: 5 });
+        assert_eq!(field.coverage_histogram(&rect), BTreeMap::from([(0, 5), (1, 6), (2, 1)]));
+        let far = Rect::new(Point { x: 0, y: 100 }, Point { x: 9, y: 109 });
+        assert_eq!(field.coverage_histogram(&far), BTreeMap::from([(0, 100)]));
+    }
 }
@@ -1,5 +1,9 @@
+use adventofcode2021::biguint::{BigUint, Count};
+use adventofcode2021::rng::Rng;
+use std::fmt::Display;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
+use std::ops::{Add, Mul};
 
 fn read_input(filename: &str) -> Vec<u32> {
     let file = File::open(filename).expect("Cannot open file");
@@ -46,33 +50,571 @@ fn simulate_fish_population_naive(timers: &Vec<u32>, days: u32) -> u32 {
     timers.len() as u32
 }
 
-fn simulate_fish_population(timers: &Vec<u32>, days: u32) -> u64 {
-    const REPRODUCTION_TIME: usize = 7;
-    const INFERTILITY_TIME: usize = 2;
+/// Life cycle of a species whose members are described by a timer, like the
+/// lanternfish. An adult produces a litter every `cycle_length` days, a
+/// newborn needs `juvenile_delay` additional days before its first litter.
+#[derive(Debug, Clone)]
+struct Species {
+    name: String,
+    cycle_length: usize,
+    juvenile_delay: usize,
+    litter_size: u64,
+    /// Mortality: if given, a member dies right after its n-th litter.
+    max_litters: Option<usize>,
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum SpeciesError {
+    /// Members need at least one day between litters.
+    ZeroCycleLength,
+    /// With mortality, members have at least one litter.
+    ZeroMaxLitters,
+}
 
-    const N: usize = REPRODUCTION_TIME + INFERTILITY_TIME;
-    let mut fish_counts: [u64; N] = [0; N];
-    for &timer in timers {
-        fish_counts[timer as usize] += 1;
+impl Species {
+    fn new(
+        name: &str,
+        cycle_length: usize,
+        juvenile_delay: usize,
+        litter_size: u64,
+        max_litters: Option<usize>,
+    ) -> Result<Species, SpeciesError> {
+        if cycle_length == 0 {
+            return Err(SpeciesError::ZeroCycleLength);
+        }
+        if max_litters == Some(0) {
+            return Err(SpeciesError::ZeroMaxLitters);
+        }
+
+        Ok(Species { name: name.to_string(), cycle_length, juvenile_delay, litter_size, max_litters })
     }
 
-    for _day in 1..=days {
-        let new_fishes = fish_counts[0];
-        for i in 1..fish_counts.len() {
-            fish_counts[i - 1] = fish_counts[i];
+    fn lanternfish() -> Species {
+        Species::new("lanternfish", 7, 2, 1, None).unwrap()
+    }
+
+    /// Number of distinct timer values.
+    fn timers(&self) -> usize {
+        self.cycle_length + self.juvenile_delay
+    }
+
+    /// Members are grouped by timer and, with mortality, by the number of
+    /// litters they have had.
+    fn buckets(&self) -> usize {
+        self.timers() * self.max_litters.unwrap_or(1)
+    }
+
+    fn bucket(&self, litters: usize, timer: usize) -> usize {
+        litters * self.timers() + timer
+    }
+
+    fn bucket_name(&self, bucket: usize) -> String {
+        match self.max_litters {
+            None => format!("timer{}", bucket),
+            Some(_) => format!("litters{}_timer{}", bucket / self.timers(), bucket % self.timers()),
+        }
+    }
+
+    /// Factor by which the population grows per day in the long run. It is the
+    /// root λ of the Euler-Lotka equation: the litters of a member, born at the
+    /// ages `juvenile_delay + k * cycle_length`, discounted by λ per day add up
+    /// to one. For the lanternfish this is the real root of λ^9 = λ^2 + 1.
+    fn asymptotic_growth(&self) -> f64 {
+        let offspring = |lambda: f64| {
+            let first = lambda.powi(-((self.juvenile_delay + self.cycle_length) as i32));
+            let ratio = lambda.powi(-(self.cycle_length as i32));
+            let litters = match self.max_litters {
+                Some(max) => (0..max).map(|k| ratio.powi(k as i32)).sum(),
+                None => 1.0 / (1.0 - ratio),
+            };
+            self.litter_size as f64 * first * litters
+        };
+
+        // Offspring decrease with λ and are at most one at litter_size + 1.
+        let mut low = 1.0;
+        let mut high = self.litter_size as f64 + 1.0;
+        for _ in 0..100 {
+            let mid = (low + high) / 2.0;
+            if offspring(mid) > 1.0 {
+                low = mid;
+            } else {
+                high = mid;
+            }
+        }
+        low
+    }
+}
+
+/// Members of one or more species, counted per bucket of their species.
+/// Counting in `BigUint` keeps long simulations exact.
+#[derive(Debug, Clone)]
+struct Population<T: Count = u64> {
+    species: Vec<Species>,
+    counts: Vec<Vec<T>>,
+}
+
+fn increase<T: Count>(count: &mut T, by: T) {
+    *count = count.clone() + by;
+}
+
+impl<T: Count> Population<T> {
+    fn new(species: Vec<Species>) -> Population<T> {
+        let counts = species.iter().map(|s| vec![T::from(0); s.buckets()]).collect();
+        Population { species, counts }
+    }
+
+    /// Add members of a species with the given timers that have not had any
+    /// litters yet.
+    fn add(&mut self, species: usize, timers: &[u32]) {
+        for &timer in timers {
+            let timer = timer as usize;
+            assert!(timer < self.species[species].timers(), "Timer {} is too large for {}", timer, self.species[species].name);
+            increase(&mut self.counts[species][timer], T::from(1));
+        }
+    }
+
+    fn step(&mut self) {
+        for (species, counts) in self.species.iter().zip(self.counts.iter_mut()) {
+            let litter_classes = species.max_litters.unwrap_or(1);
+            let mut next = vec![T::from(0); counts.len()];
+            let mut newborns = T::from(0);
+
+            for litters in 0..litter_classes {
+                for timer in 0..species.timers() {
+                    let count = counts[species.bucket(litters, timer)].clone();
+                    if timer > 0 {
+                        increase(&mut next[species.bucket(litters, timer - 1)], count);
+                    } else {
+                        increase(&mut newborns, count.clone() * T::from(species.litter_size));
+                        match species.max_litters {
+                            None => increase(&mut next[species.bucket(0, species.cycle_length - 1)], count),
+                            Some(max) if litters + 1 < max => {
+                                increase(&mut next[species.bucket(litters + 1, species.cycle_length - 1)], count)
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+            increase(&mut next[species.bucket(0, species.timers() - 1)], newborns);
+
+            *counts = next;
+        }
+    }
+
+    fn simulate(&mut self, days: u32) {
+        for _day in 1..=days {
+            self.step();
+        }
+    }
+
+    fn species_total(&self, species: usize) -> T {
+        self.counts[species].iter().fold(T::from(0), |sum, count| sum + count.clone())
+    }
+
+    fn total(&self) -> T {
+        (0..self.species.len()).fold(T::from(0), |sum, s| sum + self.species_total(s))
+    }
+}
+
+fn simulate_fish_population(timers: &[u32], days: u32) -> u64 {
+    let mut population = Population::new(vec![Species::lanternfish()]);
+    population.add(0, timers);
+    population.simulate(days);
+
+    population.total()
+}
+
+/// Like `simulate_fish_population`, but exact for any number of days.
+#[cfg(test)]
+fn simulate_fish_population_big(timers: &[u32], days: u32) -> BigUint {
+    let mut population = Population::new(vec![Species::lanternfish()]);
+    population.add(0, timers);
+    population.simulate(days);
+
+    population.total()
+}
+
+/// Bucket counts of a single species on every day of a simulation, to chart
+/// the growth of the population.
+#[derive(Debug)]
+struct History<T: Count = u64> {
+    species: Species,
+    counts: Vec<Vec<T>>,
+}
+
+impl<T: Count + Display> History<T> {
+    fn record(species: Species, timers: &[u32], days: u32) -> History<T> {
+        let mut population = Population::new(vec![species.clone()]);
+        population.add(0, timers);
+
+        let mut counts = vec![population.counts[0].clone()];
+        for _day in 1..=days {
+            population.step();
+            counts.push(population.counts[0].clone());
+        }
+
+        History { species, counts }
+    }
+
+    fn total(&self, day: usize) -> T {
+        self.counts[day].iter().fold(T::from(0), |sum, count| sum + count.clone())
+    }
+
+    /// Ratio of the totals on `day` and on the day before.
+    fn growth(&self, day: usize) -> Option<f64> {
+        if day == 0 {
+            return None;
+        }
+        let previous = self.total(day - 1).to_f64();
+        if previous == 0.0 {
+            None
+        } else {
+            Some(self.total(day).to_f64() / previous)
+        }
+    }
+
+    fn to_csv(&self) -> String {
+        let names: Vec<String> = (0..self.species.buckets()).map(|b| self.species.bucket_name(b)).collect();
+        let mut csv = format!("day,{},total,growth\n", names.join(","));
+
+        for (day, counts) in self.counts.iter().enumerate() {
+            let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+            let growth = self.growth(day).map_or(String::new(), |g| format!("{:.6}", g));
+            csv += &format!("{},{},{},{}\n", day, counts.join(","), self.total(day), growth);
+        }
+
+        csv
+    }
+
+    fn to_json(&self) -> String {
+        let names: Vec<String> = (0..self.species.buckets())
+            .map(|b| format!("\"{}\"", self.species.bucket_name(b)))
+            .collect();
+        let days: Vec<String> = self
+            .counts
+            .iter()
+            .enumerate()
+            .map(|(day, counts)| {
+                let counts: Vec<String> = counts.iter().map(|c| c.to_string()).collect();
+                let growth = self.growth(day).map_or("null".to_string(), |g| format!("{:.6}", g));
+                format!(
+                    "{{\"day\": {}, \"counts\": [{}], \"total\": {}, \"growth\": {}}}",
+                    day,
+                    counts.join(", "),
+                    self.total(day),
+                    growth,
+                )
+            })
+            .collect();
+
+        format!(
+            "{{\"species\": \"{}\", \"asymptotic_growth\": {:.6}, \"buckets\": [{}], \"days\": [{}]}}",
+            self.species.name,
+            self.species.asymptotic_growth(),
+            names.join(", "),
+            days.join(", "),
+        )
+    }
+}
+
+/// Number of lanternfish in each timer bucket and in total on every day.
+fn simulate_fish_population_history(timers: &[u32], days: u32) -> History<BigUint> {
+    History::record(Species::lanternfish(), timers, days)
+}
+
+/// Integer modulo `modulus`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ModInt {
+    value: u64,
+    modulus: u64,
+}
+
+impl ModInt {
+    fn new(value: u64, modulus: u64) -> ModInt {
+        assert!(modulus > 0, "Modulus must be positive");
+        ModInt { value: value % modulus, modulus }
+    }
+}
+
+impl Add for ModInt {
+    type Output = ModInt;
+
+    fn add(self, other: ModInt) -> ModInt {
+        ModInt::new(((self.value as u128 + other.value as u128) % self.modulus as u128) as u64, self.modulus)
+    }
+}
+
+impl Mul for ModInt {
+    type Output = ModInt;
+
+    fn mul(self, other: ModInt) -> ModInt {
+        ModInt::new(((self.value as u128 * other.value as u128) % self.modulus as u128) as u64, self.modulus)
+    }
+}
+
+/// Square matrix of population counts.
+#[derive(Debug, Clone, PartialEq)]
+struct Matrix<T> {
+    n: usize,
+    entries: Vec<T>,
+}
+
+impl<T: Clone + Add<Output = T> + Mul<Output = T>> Matrix<T> {
+    fn from_fn<F: Fn(usize, usize) -> T>(n: usize, f: F) -> Matrix<T> {
+        let entries = (0..n * n).map(|i| f(i / n, i % n)).collect();
+        Matrix { n, entries }
+    }
+
+    /// The matrix mapping the bucket counts of a species on one day to those
+    /// on the next. For the lanternfish this is a 9×9 matrix.
+    fn transition<F: Fn(u64) -> T>(species: &Species, convert: F) -> Matrix<T> {
+        let n = species.buckets();
+        let mut multipliers = vec![0u64; n * n];
+        let newborn = species.bucket(0, species.timers() - 1);
+
+        for litters in 0..species.max_litters.unwrap_or(1) {
+            for timer in 0..species.timers() {
+                let from = species.bucket(litters, timer);
+                if timer > 0 {
+                    multipliers[species.bucket(litters, timer - 1) * n + from] += 1;
+                } else {
+                    multipliers[newborn * n + from] += species.litter_size;
+                    match species.max_litters {
+                        None => multipliers[species.bucket(0, species.cycle_length - 1) * n + from] += 1,
+                        Some(max) if litters + 1 < max => {
+                            multipliers[species.bucket(litters + 1, species.cycle_length - 1) * n + from] += 1
+                        }
+                        Some(_) => {}
+                    }
+                }
+            }
+        }
+
+        Matrix::from_fn(n, |i, j| convert(multipliers[i * n + j]))
+    }
+
+    fn mul(&self, other: &Matrix<T>, zero: &T) -> Matrix<T> {
+        let n = self.n;
+        Matrix::from_fn(n, |i, j| {
+            (0..n).fold(zero.clone(), |sum, k| {
+                sum + self.entries[i * n + k].clone() * other.entries[k * n + j].clone()
+            })
+        })
+    }
+
+    /// Exponentiation by squaring.
+    fn pow(&self, mut exponent: u64, zero: &T, one: &T) -> Matrix<T> {
+        let mut result = Matrix::from_fn(self.n, |i, j| if i == j { one.clone() } else { zero.clone() });
+        let mut base = self.clone();
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                result = result.mul(&base, zero);
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.mul(&base, zero);
+            }
+        }
+        result
+    }
+
+    fn apply(&self, vector: &[T], zero: &T) -> Vec<T> {
+        (0..self.n)
+            .map(|i| {
+                (0..self.n).fold(zero.clone(), |sum, k| sum + self.entries[i * self.n + k].clone() * vector[k].clone())
+            })
+            .collect()
+    }
+}
+
+/// Bucket counts of a species after `days` days in O(log days) matrix products.
+fn fast_forward<T, F>(species: &Species, counts: &[u64], days: u64, convert: F) -> Vec<T>
+where
+    T: Clone + Add<Output = T> + Mul<Output = T>,
+    F: Fn(u64) -> T,
+{
+    let zero = convert(0);
+    let one = convert(1);
+    let counts: Vec<T> = counts.iter().map(|&c| convert(c)).collect();
+    Matrix::transition(species, &convert)
+        .pow(days, &zero, &one)
+        .apply(&counts, &zero)
+}
+
+fn lanternfish_counts(timers: &[u32]) -> Vec<u64> {
+    let mut population: Population = Population::new(vec![Species::lanternfish()]);
+    population.add(0, timers);
+    population.counts.remove(0)
+}
+
+/// Exact number of lanternfish after any number of days.
+fn simulate_fish_population_exact(timers: &[u32], days: u64) -> BigUint {
+    fast_forward(&Species::lanternfish(), &lanternfish_counts(timers), days, BigUint::from)
+        .into_iter()
+        .fold(BigUint::from(0), |sum, count| sum + count)
+}
+
+/// Number of lanternfish after any number of days modulo `modulus`.
+fn simulate_fish_population_mod(timers: &[u32], days: u64, modulus: u64) -> u64 {
+    fast_forward(&Species::lanternfish(), &lanternfish_counts(timers), days, |c| ModInt::new(c, modulus))
+        .into_iter()
+        .fold(ModInt::new(0, modulus), |sum, count| sum + count)
+        .value
+}
+
+/// First day with more than `threshold` lanternfish, or `None` if there are
+/// none at all. Lanternfish never die, so the population never shrinks and
+/// the day can be found by binary search over `simulate_fish_population_exact`.
+fn first_day_exceeding(timers: &[u32], threshold: &BigUint) -> Option<u64> {
+    let exceeds = |days| simulate_fish_population_exact(timers, days) > *threshold;
+
+    if timers.is_empty() {
+        return None;
+    }
+    if exceeds(0) {
+        return Some(0);
+    }
+
+    // The threshold is exceeded on day `high` but not on day `low`.
+    let mut high = 1;
+    while !exceeds(high) {
+        high *= 2;
+    }
+    let mut low = high / 2;
+    while high - low > 1 {
+        let mid = low + (high - low) / 2;
+        if exceeds(mid) {
+            high = mid;
+        } else {
+            low = mid;
+        }
+    }
+
+    Some(high)
+}
+
+/// Randomness of a stochastic simulation. With all probabilities equal to one
+/// it is the deterministic model.
+#[derive(Debug, Clone, Copy)]
+struct Chances {
+    /// Probability that a timer counts down on a given day. Otherwise it is
+    /// stuck for a day, which randomly delays spawning.
+    tick: f64,
+    /// Probability that a member whose timer runs out actually has a litter.
+    spawn: f64,
+    /// Probability that a member survives a day.
+    survival: f64,
+}
+
+impl Population<u64> {
+    /// Like `step`, but each member survives, counts down and spawns only
+    /// with the given chances.
+    fn step_randomly(&mut self, chances: &Chances, rng: &mut Rng) {
+        for (species, counts) in self.species.iter().zip(self.counts.iter_mut()) {
+            let litter_classes = species.max_litters.unwrap_or(1);
+            let mut next = vec![0; counts.len()];
+            let mut newborns = 0;
+
+            for litters in 0..litter_classes {
+                for timer in 0..species.timers() {
+                    let bucket = species.bucket(litters, timer);
+                    let survivors = rng.binomial(counts[bucket], chances.survival);
+                    let ticking = rng.binomial(survivors, chances.tick);
+                    next[bucket] += survivors - ticking;
+
+                    if timer > 0 {
+                        next[species.bucket(litters, timer - 1)] += ticking;
+                    } else {
+                        newborns += rng.binomial(ticking, chances.spawn) * species.litter_size;
+                        match species.max_litters {
+                            None => next[species.bucket(0, species.cycle_length - 1)] += ticking,
+                            Some(max) if litters + 1 < max => {
+                                next[species.bucket(litters + 1, species.cycle_length - 1)] += ticking
+                            }
+                            Some(_) => {}
+                        }
+                    }
+                }
+            }
+            next[species.bucket(0, species.timers() - 1)] += newborns;
+
+            *counts = next;
+        }
+    }
+}
+
+/// Distribution of the population on one day over all trials.
+#[derive(Debug, PartialEq)]
+struct DaySummary {
+    day: u32,
+    mean: f64,
+    variance: f64,
+    /// Pairs of percentile and population, by the nearest rank method.
+    percentiles: Vec<(f64, u64)>,
+}
+
+impl DaySummary {
+    fn new(day: u32, mut totals: Vec<u64>, percentiles: &[f64]) -> DaySummary {
+        totals.sort_unstable();
+        let n = totals.len() as f64;
+        let mean = totals.iter().map(|&t| t as f64).sum::<f64>() / n;
+        let variance = if totals.len() > 1 {
+            totals.iter().map(|&t| (t as f64 - mean).powi(2)).sum::<f64>() / (n - 1.0)
+        } else {
+            0.0
+        };
+        let percentiles = percentiles
+            .iter()
+            .map(|&p| {
+                let rank = (p / 100.0 * n).ceil() as usize;
+                (p, totals[rank.clamp(1, totals.len()) - 1])
+            })
+            .collect();
+
+        DaySummary { day, mean, variance, percentiles }
+    }
+}
+
+/// Run `trials` seeded Monte Carlo simulations of a lanternfish population
+/// and summarize the totals on each of the given days.
+fn simulate_fish_population_randomly(
+    timers: &[u32],
+    chances: &Chances,
+    days: &[u32],
+    trials: usize,
+    seed: u64,
+) -> Vec<DaySummary> {
+    assert!(trials > 0, "At least one trial is needed");
+    let mut rng = Rng::new(seed);
+    let last_day = days.iter().copied().max().unwrap_or(0);
+    let mut totals = vec![Vec::with_capacity(trials); days.len()];
+
+    for _trial in 0..trials {
+        let mut population = Population::new(vec![Species::lanternfish()]);
+        population.add(0, timers);
+
+        for day in 0..=last_day {
+            if day > 0 {
+                population.step_randomly(chances, &mut rng);
+            }
+            for (i, _) in days.iter().enumerate().filter(|(_, &d)| d == day) {
+                totals[i].push(population.total());
+            }
         }
-        fish_counts[REPRODUCTION_TIME - 1] += new_fishes;
-        fish_counts[REPRODUCTION_TIME + INFERTILITY_TIME - 1] = new_fishes;
     }
 
-    fish_counts.iter().sum()
+    days.iter()
+        .zip(totals)
+        .map(|(&day, totals)| DaySummary::new(day, totals, &[5.0, 25.0, 50.0, 75.0, 95.0]))
+        .collect()
 }
 
-fn part1(timers: &Vec<u32>) -> u64 {
+fn part1(timers: &[u32]) -> u64 {
     simulate_fish_population(timers, 80)
 }
 
-fn part2(timers: &Vec<u32>) -> u64 {
+fn part2(timers: &[u32]) -> u64 {
     simulate_fish_population(timers, 256)
 }
 
@@ -80,6 +622,75 @@ fn main() {
     let input = read_input("inputs/06.txt");
     // println!("Initial state: {:?}", input);
 
+    // Pass "days <days> [modulus]" to fast forward to any day, exactly or
+    // modulo the given number, "history <days> [csv|json]" to print the
+    // population on every day or "exceeds <count>" to find the first day with
+    // more than that many lanternfish. Pass "random <trials> <seed>
+    // <day,day,...> [tick [spawn [survival]]]" for Monte Carlo simulations with
+    // the given chances, which default to one.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("random") {
+        let trials = args.get(1).expect("Missing number of trials").parse().expect("Cannot parse number of trials");
+        let seed = args.get(2).expect("Missing seed").parse().expect("Cannot parse seed");
+        let days: Vec<u32> = args
+            .get(3)
+            .expect("Missing days")
+            .split(',')
+            .map(|d| d.parse().expect("Cannot parse day"))
+            .collect();
+        let chance = |i: usize| args.get(i).map_or(1.0, |c| c.parse().expect("Cannot parse chance"));
+        let chances = Chances { tick: chance(4), spawn: chance(5), survival: chance(6) };
+
+        for summary in simulate_fish_population_randomly(&input, &chances, &days, trials, seed) {
+            let percentiles: Vec<String> = summary
+                .percentiles
+                .iter()
+                .map(|(p, total)| format!("p{}={}", p, total))
+                .collect();
+            println!(
+                "Day {}: mean {:.1}, variance {:.1}, {}",
+                summary.day,
+                summary.mean,
+                summary.variance,
+                percentiles.join(" ")
+            );
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("history") {
+        let days = args.get(1).expect("Missing number of days").parse().expect("Cannot parse number of days");
+        let history = simulate_fish_population_history(&input, days);
+        match args.get(2).map(|arg| arg.as_str()) {
+            Some("json") => println!("{}", history.to_json()),
+            Some("csv") | None => print!("{}", history.to_csv()),
+            Some(format) => panic!("Unknown format {}", format),
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("exceeds") {
+        let threshold: BigUint = args.get(1).expect("Missing count").parse().expect("Cannot parse count");
+        match first_day_exceeding(&input, &threshold) {
+            Some(day) => println!("Day {} is the first with more than {} lanternfish", day, threshold),
+            None => println!("There will never be more than {} lanternfish", threshold),
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("days") {
+        let days: u64 = args.get(1).expect("Missing number of days").parse().expect("Cannot parse number of days");
+        match args.get(2) {
+            Some(modulus) => {
+                let modulus = modulus.parse().expect("Cannot parse modulus");
+                let count = simulate_fish_population_mod(&input, days, modulus);
+                println!("After {} days there are {} lanternfish modulo {}", days, count, modulus);
+            }
+            None => {
+                let count = simulate_fish_population_exact(&input, days);
+                println!("After {} days there are {} lanternfish", days, count);
+            }
+        }
+        return;
+    }
+
     let solution = part1(&input);
     println!("Part 1: After 80 days there are {} lanternfish", solution);
     let solution = part2(&input);
@@ -113,4 +724,148 @@ mod tests06 {
 
         assert_eq!(solution, 26984457539);
     }
+
+    #[test]
+    fn test04() {
+        // Semelparous species: every member has a litter of two and dies.
+        let salmon = Species::new("salmon", 3, 0, 2, Some(1)).unwrap();
+        // Members have two litters of three, one cycle apart.
+        let rabbit = Species::new("rabbit", 2, 1, 3, Some(2)).unwrap();
+
+        let mut population = Population::new(vec![Species::lanternfish(), salmon, rabbit]);
+        let input = read_input("test_inputs/06_01.txt");
+        population.add(0, &input);
+        population.add(1, &[0]);
+        population.add(2, &[0]);
+
+        let mut totals = Vec::new();
+        for _day in 1..=7 {
+            population.step();
+            totals.push((population.species_total(1), population.species_total(2)));
+        }
+        assert_eq!(totals, vec![(2, 4), (2, 4), (2, 6), (4, 15), (4, 15), (4, 30), (8, 57)]);
+
+        population.simulate(73);
+        assert_eq!(population.species_total(0), 5934);
+
+        assert_eq!(Species::new("mayfly", 0, 1, 1, None).unwrap_err(), SpeciesError::ZeroCycleLength);
+        assert_eq!(Species::new("mayfly", 1, 1, 1, Some(0)).unwrap_err(), SpeciesError::ZeroMaxLitters);
+    }
+
+    #[test]
+    fn test05() {
+        let input = read_input("test_inputs/06_01.txt");
+        for days in 0..=300 {
+            let count = simulate_fish_population(&input, days);
+            assert_eq!(simulate_fish_population_exact(&input, days as u64).to_string(), count.to_string());
+            assert_eq!(simulate_fish_population_mod(&input, days as u64, 1_000_000_007), count % 1_000_000_007);
+        }
+        assert_eq!(simulate_fish_population_exact(&input, 256).to_string(), "26984457539");
+        assert_eq!(simulate_fish_population_big(&input, 1000), simulate_fish_population_exact(&input, 1000));
+        assert_eq!(
+            simulate_fish_population_exact(&input, 1000).to_string(),
+            "379589061144698259131825683795505058481"
+        );
+
+        // Fast forwarding in two parts or all at once agrees.
+        let prime = 998_244_353;
+        let species = Species::lanternfish();
+        let counts = lanternfish_counts(&input);
+        let convert = |c| ModInt::new(c, prime);
+        let half = fast_forward(&species, &counts, 500_000_000_000_000, convert);
+        let half: Vec<u64> = half.iter().map(|c| c.value).collect();
+        let full = fast_forward(&species, &counts, 1_000_000_000_000_000, convert);
+        assert_eq!(fast_forward(&species, &half, 500_000_000_000_000, convert), full);
+    }
+
+    #[test]
+    fn test06() {
+        // Matrices also work for species with mortality.
+        let species = Species::new("rabbit", 2, 1, 3, Some(2)).unwrap();
+        let mut population = Population::new(vec![species.clone()]);
+        population.add(0, &[0, 1, 2]);
+        let counts = population.counts[0].clone();
+
+        for days in 0..40 {
+            let counts = fast_forward(&species, &counts, days, BigUint::from);
+            let total = counts.into_iter().fold(BigUint::from(0), |sum, count| sum + count);
+            assert_eq!(total.to_string(), population.total().to_string());
+            population.step();
+        }
+    }
+
+    #[test]
+    fn test07() {
+        let input = read_input("test_inputs/06_01.txt");
+        let history = simulate_fish_population_history(&input, 300);
+
+        for day in [0, 18, 80, 256, 300] {
+            assert_eq!(history.total(day), simulate_fish_population_exact(&input, day as u64));
+        }
+
+        let csv = history.to_csv();
+        let mut lines = csv.lines();
+        assert_eq!(lines.next(), Some("day,timer0,timer1,timer2,timer3,timer4,timer5,timer6,timer7,timer8,total,growth"));
+        assert_eq!(lines.next(), Some("0,0,1,1,2,1,0,0,0,0,5,"));
+        assert_eq!(lines.next(), Some("1,1,1,2,1,0,0,0,0,0,5,1.000000"));
+        assert!(history.to_json().starts_with("{\"species\": \"lanternfish\", \"asymptotic_growth\": 1.091024, "));
+
+        // The daily growth approaches the root of λ^9 = λ^2 + 1.
+        let lambda = Species::lanternfish().asymptotic_growth();
+        assert!((lambda - 1.0910244704807566).abs() < 1e-12);
+        assert!((history.growth(256).unwrap() - lambda).abs() < 0.01);
+
+        let salmon = Species::new("salmon", 3, 0, 2, Some(1)).unwrap();
+        assert!((salmon.asymptotic_growth() - 2f64.powf(1.0 / 3.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test08() {
+        let input = read_input("test_inputs/06_01.txt");
+
+        assert_eq!(first_day_exceeding(&input, &BigUint::from(4)), Some(0));
+        assert_eq!(first_day_exceeding(&input, &BigUint::from(1000)), Some(60));
+        assert_eq!(first_day_exceeding(&input, &BigUint::from(5933)), Some(80));
+        assert_eq!(first_day_exceeding(&input, &BigUint::from(5934)), Some(81));
+        assert_eq!(first_day_exceeding(&input, &BigUint::from(26984457539)), Some(257));
+        assert_eq!(first_day_exceeding(&[], &BigUint::from(0)), None);
+    }
+
+    #[test]
+    fn test09() {
+        // Without randomness every trial is the deterministic simulation.
+        let input = read_input("test_inputs/06_01.txt");
+        let days = [0, 18, 80, 256];
+        let certain = Chances { tick: 1.0, spawn: 1.0, survival: 1.0 };
+        let summaries = simulate_fish_population_randomly(&input, &certain, &days, 20, 1);
+
+        for (summary, &day) in summaries.iter().zip(days.iter()) {
+            let expected = simulate_fish_population(&input, day);
+            assert_eq!(summary.day, day);
+            assert_eq!(summary.mean, expected as f64);
+            assert_eq!(summary.variance, 0.0);
+            assert!(summary.percentiles.iter().all(|&(_, total)| total == expected));
+        }
+    }
+
+    #[test]
+    fn test10() {
+        let input = read_input("test_inputs/06_01.txt");
+        let chances = Chances { tick: 0.9, spawn: 0.8, survival: 0.99 };
+        let days = [10, 80];
+
+        let summaries = simulate_fish_population_randomly(&input, &chances, &days, 200, 7);
+        assert_eq!(summaries, simulate_fish_population_randomly(&input, &chances, &days, 200, 7));
+        assert!(summaries[1].mean < 5934.0);
+        assert!(summaries[1].variance > 0.0);
+        let percentiles: Vec<u64> = summaries[1].percentiles.iter().map(|&(_, total)| total).collect();
+        assert!(percentiles.windows(2).all(|w| w[0] <= w[1]));
+
+        // Members which never spawn keep the population constant, members
+        // which never survive leave nothing behind.
+        let barren = Chances { tick: 1.0, spawn: 0.0, survival: 1.0 };
+        assert_eq!(simulate_fish_population_randomly(&input, &barren, &[80], 5, 3)[0].mean, 5.0);
+        let doomed = Chances { tick: 1.0, spawn: 1.0, survival: 0.0 };
+        assert_eq!(simulate_fish_population_randomly(&input, &doomed, &[1], 5, 3)[0].mean, 0.0);
+    }
 }
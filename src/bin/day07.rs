@@ -22,17 +22,33 @@ fn read_input(filename: &str) -> Vec<u32> {
     input
 }
 
-fn calc_abs_deviation(list: &Vec<u32>, v: u32) -> u32 {
+/// Crabs on a plane, given as `x,y` pairs separated by whitespace.
+fn read_input_2d(filename: &str) -> Vec<(u32, u32)> {
+    let input = std::fs::read_to_string(filename).expect("Cannot open file");
+
+    input
+        .split_whitespace()
+        .map(|pair| {
+            let (x, y) = pair.split_once(',').expect("Positions must be x,y pairs.");
+            (
+                x.parse().expect("Coordinates must be integers."),
+                y.parse().expect("Coordinates must be integers."),
+            )
+        })
+        .collect()
+}
+
+fn calc_abs_deviation(list: &[u32], v: u32) -> u64 {
     list.iter()
-        .map(|&x| if x > v { x - v } else { v - x })
+        .map(|&x| x.abs_diff(v) as u64)
         .sum()
 }
 
-fn part1(positions: &Vec<u32>) -> u32 {
+fn part1(positions: &[u32]) -> u64 {
     // The solution for this task is to determine the median. The
     // median m minimizes the absolute deviation
     // (i.e. \Sum_{i=1}^{N} |x_i - m| ), which is just the task at hand.
-    let mut p = positions.clone();
+    let mut p = positions.to_vec();
     p.sort();
 
     let i_mid = p.len() / 2;
@@ -45,33 +61,329 @@ fn part1(positions: &Vec<u32>) -> u32 {
     }
 }
 
-fn calc_fuel(positions: &Vec<u32>, target: u32) -> u32 {
-    positions
-        .iter()
-        .map(|&p| {
-            let distance = if p > target { p - target } else { target - p };
+/// Fuel a crab needs to move a given distance.
+trait FuelCost {
+    fn fuel(&self, distance: u64) -> u64;
+
+    /// Whether the fuel is convex and non-decreasing in the distance. Then the
+    /// total fuel is convex in the target position, too.
+    fn is_convex(&self) -> bool {
+        false
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Cost {
+    /// One unit of fuel per step.
+    Linear,
+    /// Each step costs one unit more than the one before.
+    Triangular,
+    /// The distance squared.
+    Quadratic,
+}
+
+impl FuelCost for Cost {
+    fn fuel(&self, distance: u64) -> u64 {
+        match self {
+            Cost::Linear => distance,
             // Use sum formula:
             // \Sum_{i=1}^\text{distance} i = \frac{\text{distance} \cdot (\text{distance} + 1)}{2}
-            let fuel = distance * (distance + 1) / 2;
-            fuel
-        })
+            Cost::Triangular => distance * (distance + 1) / 2,
+            Cost::Quadratic => distance * distance,
+        }
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+/// Any function of the distance is a cost, but only found exactly.
+impl<F: Fn(u64) -> u64> FuelCost for F {
+    fn fuel(&self, distance: u64) -> u64 {
+        self(distance)
+    }
+}
+
+/// User-defined cost which the user promises to be convex and
+/// non-decreasing, so it can be optimized by ternary search.
+struct Convex<F: Fn(u64) -> u64>(F);
+
+impl<F: Fn(u64) -> u64> FuelCost for Convex<F> {
+    fn fuel(&self, distance: u64) -> u64 {
+        (self.0)(distance)
+    }
+
+    fn is_convex(&self) -> bool {
+        true
+    }
+}
+
+fn calc_fuel<C: FuelCost + ?Sized>(positions: &[u32], target: u32, cost: &C) -> u64 {
+    positions
+        .iter()
+        .map(|&p| cost.fuel(p.abs_diff(target) as u64))
         .sum()
 }
 
-fn part2(positions: &Vec<u32>) -> u32 {
-    // The optimum value lies around the mean position. To be more specific in the 
-    // range p_mean - 1/2 <= p_opt <= p_mean + 1/2.
-    // As we are working with whole numbers, we extend that range to +/- 1 around 
-    // the mean position.
-    let mean: u32 = positions.iter().cloned().sum::<u32>() / positions.len() as u32;
-    (mean-1..=mean+1)
-        .map(|p| {
-            let fuel = calc_fuel(&positions, p);
-            // println!("Moving to {} requires {} fuel.", p, fuel);
-            fuel
-        })
-        .min()
-        .unwrap()
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Search {
+    /// Try every position between the leftmost and the rightmost crab.
+    Scan,
+    /// Ternary search, only correct for convex costs.
+    Ternary,
+}
+
+/// Best position to align at and the fuel needed. Of several equally good
+/// positions, the leftmost one is chosen.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Alignment {
+    position: u32,
+    fuel: u64,
+}
+
+/// Best position between the leftmost and the rightmost crab, which is where
+/// the optimum of a non-decreasing cost lies. `None` without crabs.
+fn optimize<C: FuelCost + ?Sized>(positions: &[u32], cost: &C, search: Search) -> Option<Alignment> {
+    let low = *positions.iter().min()?;
+    let high = *positions.iter().max()?;
+    Some(minimize(low, high, |target| calc_fuel(positions, target, cost), search))
+}
+
+/// Leftmost position between `low` and `high` with the least fuel.
+fn minimize<F: Fn(u32) -> u64>(mut low: u32, mut high: u32, fuel: F, search: Search) -> Alignment {
+    if search == Search::Ternary {
+        // Shrink [low, high] while keeping the leftmost optimum inside.
+        while high - low > 2 {
+            let third = (high - low) / 3;
+            let (m1, m2) = (low + third, high - third);
+            let (f1, f2) = (fuel(m1), fuel(m2));
+            if f1 < f2 {
+                high = m2 - 1;
+            } else if f1 > f2 {
+                low = m1 + 1;
+            } else {
+                high = m2;
+            }
+        }
+    }
+
+    (low..=high)
+        .map(|position| Alignment { position, fuel: fuel(position) })
+        .min_by_key(|alignment| (alignment.fuel, alignment.position))
+        .expect("Cannot minimize over an empty range")
+}
+
+/// Optimize by ternary search if the cost allows it, otherwise by scanning.
+fn align<C: FuelCost + ?Sized>(positions: &[u32], cost: &C) -> Option<Alignment> {
+    let search = if cost.is_convex() { Search::Ternary } else { Search::Scan };
+    optimize(positions, cost, search)
+}
+
+/// Crabs aligning at the same position.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Cluster {
+    position: u32,
+    size: usize,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Clustering {
+    clusters: Vec<Cluster>,
+    fuel: u64,
+}
+
+/// Prefix sums of sorted positions and of their squares, which give the fuel
+/// of any run of crabs in O(log n).
+struct RunSums<'a> {
+    sorted: &'a [u32],
+    sums: Vec<u128>,
+    squares: Vec<u128>,
+}
+
+impl<'a> RunSums<'a> {
+    fn new(sorted: &'a [u32]) -> Self {
+        let mut sums = vec![0];
+        let mut squares = vec![0];
+        for &x in sorted {
+            let x = x as u128;
+            sums.push(sums[sums.len() - 1] + x);
+            squares.push(squares[squares.len() - 1] + x * x);
+        }
+        RunSums { sorted, sums, squares }
+    }
+
+    /// Fuel for the crabs i..j to align at `target`.
+    fn fuel(&self, i: usize, j: usize, target: u32, cost: Cost) -> u64 {
+        let t = target as u128;
+        let sum = |from: usize, to: usize| self.sums[to] - self.sums[from];
+
+        // \Sum |x - t| splits into the crabs left and right of the target.
+        let split = i + self.sorted[i..j].partition_point(|&x| x < target);
+        let (left, right) = ((split - i) as u128, (j - split) as u128);
+        let deviation = t * left - sum(i, split) + sum(split, j) - t * right;
+
+        // \Sum (x - t)^2 = \Sum x^2 - 2 t \Sum x + n t^2, where x^2 + t^2 >= 2 t x
+        // keeps the difference from underflowing.
+        let n = (j - i) as u128;
+        let squared = self.squares[j] - self.squares[i] + n * t * t - 2 * t * sum(i, j);
+
+        let fuel = match cost {
+            Cost::Linear => deviation,
+            Cost::Triangular => (squared + deviation) / 2,
+            Cost::Quadratic => squared,
+        };
+        u64::try_from(fuel).unwrap_or(u64::MAX)
+    }
+
+    /// Best alignment of the crabs i..j, all costs being convex.
+    fn align(&self, i: usize, j: usize, cost: Cost) -> Alignment {
+        minimize(self.sorted[i], self.sorted[j - 1], |target| self.fuel(i, j, target, cost), Search::Ternary)
+    }
+}
+
+/// Align the crabs at up to `k` positions with the least total fuel. Every
+/// crab moves to the closest of the positions, so the clusters are runs of
+/// the sorted positions and can be found by dynamic programming in O(k n^2),
+/// after aligning each of the O(n^2) runs with `RunSums` in O(log^2 n).
+/// There are at most as many clusters as distinct positions, as more would
+/// only repeat a position. `None` if there is nothing to align.
+fn cluster(positions: &[u32], k: usize, cost: Cost) -> Option<Clustering> {
+    if positions.is_empty() || k == 0 {
+        return None;
+    }
+    let mut sorted = positions.to_vec();
+    sorted.sort_unstable();
+    let n = sorted.len();
+    let distinct = 1 + sorted.windows(2).filter(|pair| pair[0] != pair[1]).count();
+    let k = k.min(distinct);
+
+    // runs[i][j - i - 1] aligns the crabs i..j.
+    let sums = RunSums::new(&sorted);
+    let runs: Vec<Vec<Alignment>> = (0..n)
+        .map(|i| (i + 1..=n).map(|j| sums.align(i, j, cost)).collect())
+        .collect();
+
+    // best[c][j] is the least fuel to align the first j crabs at c positions
+    // together with the start of the last cluster.
+    let mut best: Vec<Vec<Option<(u64, usize)>>> = vec![vec![None; n + 1]; k + 1];
+    best[0][0] = Some((0, 0));
+    for c in 1..=k {
+        for j in c..=n {
+            for i in c - 1..j {
+                if let Some((fuel, _)) = best[c - 1][i] {
+                    let fuel = fuel + runs[i][j - i - 1].fuel;
+                    if best[c][j].is_none_or(|(least, _)| fuel < least) {
+                        best[c][j] = Some((fuel, i));
+                    }
+                }
+            }
+        }
+    }
+
+    let (fuel, _) = best[k][n]?;
+    let mut clusters = Vec::with_capacity(k);
+    let mut end = n;
+    for c in (1..=k).rev() {
+        let (_, start) = best[c][end]?;
+        clusters.push(Cluster { position: runs[start][end - start - 1].position, size: end - start });
+        end = start;
+    }
+    clusters.reverse();
+
+    Some(Clustering { clusters, fuel })
+}
+
+/// Best meeting point on the plane and the fuel needed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct PlaneAlignment {
+    x: u32,
+    y: u32,
+    fuel: u64,
+}
+
+/// Meeting point for crabs moving along the axes, where a move costs the sum
+/// of the fuel along either axis. The axes separate, so each is aligned on
+/// its own. `None` without crabs.
+fn align_manhattan<C: FuelCost + ?Sized>(crabs: &[(u32, u32)], cost: &C) -> Option<PlaneAlignment> {
+    let xs: Vec<u32> = crabs.iter().map(|&(x, _)| x).collect();
+    let ys: Vec<u32> = crabs.iter().map(|&(_, y)| y).collect();
+    let x = align(&xs, cost)?;
+    let y = align(&ys, cost)?;
+
+    Some(PlaneAlignment { x: x.position, y: y.position, fuel: x.fuel + y.fuel })
+}
+
+/// Point minimizing the sum of Euclidean distances to the crabs.
+#[derive(Debug, Clone, Copy, PartialEq)]
+struct GeometricMedian {
+    x: f64,
+    y: f64,
+    distance: f64,
+}
+
+/// Geometric median by Weiszfeld's algorithm, starting at the centroid, with
+/// the Vardi-Zhang modification for estimates on top of a crab. Iteration
+/// stops once a step moves the estimate less than `tolerance`, or after
+/// 10000 steps. `None` without crabs.
+fn geometric_median(crabs: &[(u32, u32)], tolerance: f64) -> Option<GeometricMedian> {
+    if crabs.is_empty() {
+        return None;
+    }
+    let points: Vec<(f64, f64)> = crabs.iter().map(|&(x, y)| (x as f64, y as f64)).collect();
+    let n = points.len() as f64;
+    let mut estimate = (
+        points.iter().map(|p| p.0).sum::<f64>() / n,
+        points.iter().map(|p| p.1).sum::<f64>() / n,
+    );
+
+    for _step in 0..10000 {
+        // Weighted mean of the other crabs, the pull towards them and the
+        // number of crabs right at the estimate.
+        let mut weights = 0.0;
+        let mut mean = (0.0, 0.0);
+        let mut pull = (0.0, 0.0);
+        let mut coinciding = 0.0;
+        for &(x, y) in points.iter() {
+            let d = (x - estimate.0).hypot(y - estimate.1);
+            if d < 1e-12 {
+                coinciding += 1.0;
+                continue;
+            }
+            weights += 1.0 / d;
+            mean = (mean.0 + x / d, mean.1 + y / d);
+            pull = (pull.0 + (x - estimate.0) / d, pull.1 + (y - estimate.1) / d);
+        }
+        if weights == 0.0 {
+            break;
+        }
+        mean = (mean.0 / weights, mean.1 / weights);
+
+        let r = pull.0.hypot(pull.1);
+        let next = if r <= coinciding {
+            // The crab at the estimate holds it in place.
+            estimate
+        } else {
+            let share = coinciding / r;
+            (
+                (1.0 - share) * mean.0 + share * estimate.0,
+                (1.0 - share) * mean.1 + share * estimate.1,
+            )
+        };
+
+        let step = (next.0 - estimate.0).hypot(next.1 - estimate.1);
+        estimate = next;
+        if step < tolerance {
+            break;
+        }
+    }
+
+    let distance = points.iter().map(|&(x, y)| (x - estimate.0).hypot(y - estimate.1)).sum();
+    Some(GeometricMedian { x: estimate.0, y: estimate.1, distance })
+}
+
+fn part2(positions: &[u32]) -> u64 {
+    align(positions, &Cost::Triangular).expect("No crabs to align").fuel
 }
 
 fn main() {
@@ -79,6 +391,70 @@ fn main() {
     // let input = read_input("test_inputs/07_01.txt");
     // println!("{:?}", input);
 
+    // Pass "linear", "triangular", "quadratic" or "power <exponent>" to print
+    // the best position for that cost, or "clusters <k> [triangular]" to
+    // align at up to k positions. Pass "plane <file> [linear|triangular|euclidean]"
+    // to find the meeting point of crabs on a plane.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("plane") {
+        let crabs = read_input_2d(args.get(1).expect("Missing file"));
+        match args.get(2).map(|arg| arg.as_str()) {
+            Some("euclidean") => {
+                let median = geometric_median(&crabs, 1e-9).expect("No crabs to align");
+                println!(
+                    "Meeting at ({:.6}, {:.6}) requires a distance of {:.6}.",
+                    median.x, median.y, median.distance
+                );
+            }
+            cost => {
+                let cost = match cost {
+                    Some("triangular") => Cost::Triangular,
+                    Some("linear") | None => Cost::Linear,
+                    Some(cost) => panic!("Unknown cost {}", cost),
+                };
+                let alignment = align_manhattan(&crabs, &cost).expect("No crabs to align");
+                println!(
+                    "Meeting at ({}, {}) requires {} fuel.",
+                    alignment.x, alignment.y, alignment.fuel
+                );
+            }
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("clusters") {
+        let k = args.get(1).expect("Missing number of clusters").parse().expect("Cannot parse number of clusters");
+        let cost = match args.get(2).map(|arg| arg.as_str()) {
+            Some("triangular") => Cost::Triangular,
+            Some("linear") | None => Cost::Linear,
+            Some(cost) => panic!("Unknown cost {}", cost),
+        };
+        let clustering = cluster(&input, k, cost).expect("No crabs to align");
+        for c in clustering.clusters.iter() {
+            println!("{} crabs align at {}", c.size, c.position);
+        }
+        println!("This requires {} fuel.", clustering.fuel);
+        return;
+    }
+    let alignment = match args.first().map(|arg| arg.as_str()) {
+        Some("linear") => align(&input, &Cost::Linear),
+        Some("triangular") => align(&input, &Cost::Triangular),
+        Some("quadratic") => align(&input, &Cost::Quadratic),
+        Some("power") => {
+            let exponent: u32 = args.get(1).expect("Missing exponent").parse().expect("Cannot parse exponent");
+            assert!(exponent >= 1, "Exponent must be at least one");
+            align(&input, &Convex(move |d: u64| d.pow(exponent)))
+        }
+        Some(cost) => panic!("Unknown cost {}", cost),
+        None => None,
+    };
+    if let Some(alignment) = alignment {
+        println!(
+            "Aligning at {} requires {} fuel.",
+            alignment.position, alignment.fuel
+        );
+        return;
+    }
+
     let solution = part1(&input);
     println!(
         "Part 1: The best position for alignment requires {} fuel.",
@@ -111,4 +487,108 @@ mod tests07 {
 
         assert_eq!(solution, 168);
     }
+
+    #[test]
+    fn test03() {
+        let input = read_input("test_inputs/07_01.txt");
+
+        assert_eq!(align(&input, &Cost::Linear), Some(Alignment { position: 2, fuel: 37 }));
+        assert_eq!(align(&input, &Cost::Triangular), Some(Alignment { position: 5, fuel: 168 }));
+        assert_eq!(align(&input, &Cost::Quadratic), optimize(&input, &Cost::Quadratic, Search::Scan));
+        assert_eq!(align(&[] as &[u32], &Cost::Linear), None);
+
+        // Crabs at zero used to underflow the mean heuristic.
+        assert_eq!(part2(&[0, 0, 1]), 1);
+        assert_eq!(align(&[0, 0, 0], &Cost::Triangular), Some(Alignment { position: 0, fuel: 0 }));
+    }
+
+    #[test]
+    fn test04() {
+        let input = read_input("inputs/07.txt");
+
+        for cost in [Cost::Linear, Cost::Triangular, Cost::Quadratic] {
+            assert_eq!(align(&input, &cost), optimize(&input, &cost, Search::Scan));
+        }
+        assert_eq!(align(&input, &Cost::Linear).unwrap().fuel, part1(&input));
+
+        let cubic = Convex(|d: u64| d * d * d);
+        assert_eq!(align(&input, &cubic), optimize(&input, &cubic, Search::Scan));
+
+        // Concave costs are not convex, so they are scanned.
+        let concave = |d: u64| (d as f64).sqrt() as u64;
+        let scanned = optimize(&input, &concave, Search::Scan);
+        assert_eq!(align(&input, &concave), scanned);
+        assert!((0..2000).all(|p| calc_fuel(&input, p, &concave) >= scanned.unwrap().fuel));
+    }
+
+    #[test]
+    fn test05() {
+        let input = read_input("test_inputs/07_01.txt");
+        let clusters = |k, cost| cluster(&input, k, cost).unwrap();
+
+        assert_eq!(clusters(1, Cost::Linear).clusters, vec![Cluster { position: 2, size: 10 }]);
+        assert_eq!(clusters(1, Cost::Linear).fuel, 37);
+        assert_eq!(clusters(1, Cost::Triangular).fuel, 168);
+
+        let expected = vec![Cluster { position: 2, size: 8 }, Cluster { position: 14, size: 2 }];
+        assert_eq!(clusters(2, Cost::Linear), Clustering { clusters: expected, fuel: 13 });
+        let expected = vec![Cluster { position: 2, size: 8 }, Cluster { position: 15, size: 2 }];
+        assert_eq!(clusters(2, Cost::Triangular), Clustering { clusters: expected, fuel: 25 });
+
+        assert_eq!(clusters(3, Cost::Linear).fuel, 8);
+        assert_eq!(clusters(3, Cost::Triangular).fuel, 10);
+        let all = clusters(20, Cost::Triangular);
+        assert_eq!((all.clusters.len(), all.fuel), (7, 0));
+        assert_eq!(cluster(&input, 0, Cost::Linear), None);
+
+        // Repeated positions do not make repeated clusters.
+        let expected = vec![Cluster { position: 3, size: 3 }, Cluster { position: 8, size: 1 }];
+        assert_eq!(cluster(&[3, 3, 8, 3], 3, Cost::Linear).unwrap(), Clustering { clusters: expected, fuel: 0 });
+
+        // The prefix sums agree with summing up the fuel of every crab.
+        let mut sorted = input.clone();
+        sorted.sort_unstable();
+        let sums = RunSums::new(&sorted);
+        for cost in [Cost::Linear, Cost::Triangular, Cost::Quadratic] {
+            for i in 0..sorted.len() {
+                for j in i + 1..=sorted.len() {
+                    for target in 0..=20 {
+                        assert_eq!(sums.fuel(i, j, target, cost), calc_fuel(&sorted[i..j], target, &cost));
+                    }
+                    assert_eq!(sums.align(i, j, cost), align(&sorted[i..j], &cost).unwrap());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test06() {
+        let crabs = read_input_2d("test_inputs/07_02.txt");
+
+        assert_eq!(align_manhattan(&crabs, &Cost::Linear), Some(PlaneAlignment { x: 2, y: 3, fuel: 62 }));
+        assert_eq!(align_manhattan(&crabs, &Cost::Triangular), Some(PlaneAlignment { x: 5, y: 4, fuel: 223 }));
+
+        // Crabs on a line are the one-dimensional puzzle.
+        let input = read_input("test_inputs/07_01.txt");
+        let line: Vec<(u32, u32)> = input.iter().map(|&x| (x, 0)).collect();
+        assert_eq!(align_manhattan(&line, &Cost::Triangular), Some(PlaneAlignment { x: 5, y: 0, fuel: 168 }));
+    }
+
+    #[test]
+    fn test07() {
+        let crabs = read_input_2d("test_inputs/07_02.txt");
+        let median = geometric_median(&crabs, 1e-9).unwrap();
+        assert!((median.distance - 51.8625).abs() < 1e-3);
+        assert!((median.x - 3.57).abs() < 0.01 && (median.y - 4.05).abs() < 0.01);
+
+        let square = geometric_median(&[(0, 0), (0, 4), (4, 0), (4, 4)], 1e-9).unwrap();
+        assert!((square.x - 2.0).abs() < 1e-6 && (square.y - 2.0).abs() < 1e-6);
+        assert!((square.distance - 8.0 * 2f64.sqrt()).abs() < 1e-6);
+
+        // The median may be one of the crabs.
+        let line = geometric_median(&[(0, 0), (1, 0), (10, 0)], 1e-9).unwrap();
+        assert!((line.x - 1.0).abs() < 1e-6 && line.y.abs() < 1e-6);
+        assert!((line.distance - 10.0).abs() < 1e-6);
+        assert_eq!(geometric_median(&[], 1e-9), None);
+    }
 }
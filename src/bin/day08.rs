@@ -1,9 +1,6 @@
 //! This is a solution to day 8 but the second part is quite extensive 
 //! because I missed the point that the first ten numbers are the 
-//! numbers from zero to nine. The algorithm implemented here assumes 
-//! only that the given encoded numbers can be uniquely resolved. 
-//! Otherwise, the algirithm is quite general and will find a solution,
-//! possibly not even considering all numbers. It generates rules first
+//! numbers from zero to nine. `decode_entry` generates rules first
 //! based on the few numbers directly recognizable (1, 4, 7 
 //! and 8 [although 8 is actually no use to generate rules]). These 
 //! numbers can be used to generate rules which are the possible 
@@ -13,114 +10,205 @@
 //! 
 //! In the end we retrieve a mapping of the encoded segments to the 
 //! clear segments, which is used to calculate the displayed value.
-
-use std::collections::{HashMap, HashSet};
+//! This path assumes that the given encoded numbers can be uniquely
+//! resolved and gives up otherwise.
+//!
+//! The symbols of the display are given as data by a `GlyphTable`, so other
+//! displays like hex digits work, too. The directly recognizable symbols are
+//! those with a number of segments no other symbol has.
+//!
+//! `solve_entry` does not assume a unique solution: it searches for all
+//! wirings explaining an entry, up to a limit, and reports the digits which
+//! stay ambiguous, or the patterns which conflict if there is no wiring at all.
+
+use adventofcode2021::rng::Rng;
+use std::collections::{BTreeSet, HashMap, HashSet};
 use std::fmt::Debug;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
 use std::str::FromStr;
 
+/// Segment of a display, named by a letter starting at `a`.
 #[derive(PartialEq, Eq, Hash, Clone, Copy)]
-enum Segment {
-    A,
-    B,
-    C,
-    D,
-    E,
-    F,
-    G,
-}
+struct Segment(u8);
 
 impl Segment {
-    const fn members() -> [Segment; 7] {
-        [
-            Segment::A,
-            Segment::B,
-            Segment::C,
-            Segment::D,
-            Segment::E,
-            Segment::F,
-            Segment::G,
-        ]
-    }
-
-    fn get_segments(number: u8) -> Vec<Segment> {
-        match number {
-            0 => [
-                Segment::A,
-                Segment::B,
-                Segment::C,
-                Segment::E,
-                Segment::F,
-                Segment::G,
-            ]
-            .to_vec(),
-            1 => [Segment::C, Segment::F].to_vec(),
-            2 => [Segment::A, Segment::C, Segment::D, Segment::E, Segment::G].to_vec(),
-            3 => [Segment::A, Segment::C, Segment::D, Segment::F, Segment::G].to_vec(),
-            4 => [Segment::B, Segment::C, Segment::D, Segment::F].to_vec(),
-            5 => [Segment::A, Segment::B, Segment::D, Segment::F, Segment::G].to_vec(),
-            6 => [
-                Segment::A,
-                Segment::B,
-                Segment::D,
-                Segment::E,
-                Segment::F,
-                Segment::G,
-            ]
-            .to_vec(),
-            7 => [Segment::A, Segment::C, Segment::F].to_vec(),
-            8 => Self::members().to_vec(),
-            9 => [
-                Segment::A,
-                Segment::B,
-                Segment::C,
-                Segment::D,
-                Segment::F,
-                Segment::G,
-            ]
-            .to_vec(),
-            _ => [].to_vec(),
-        }
+    /// Displays have at most 16 segments, `a` to `p`.
+    const MAX: u8 = 16;
+
+    fn members(count: usize) -> impl Iterator<Item = Segment> {
+        (0..count as u8).map(Segment)
     }
 }
 
 impl Debug for Segment {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        let letter = match self {
-            Segment::A => 'a',
-            Segment::B => 'b',
-            Segment::C => 'c',
-            Segment::D => 'd',
-            Segment::E => 'e',
-            Segment::F => 'f',
-            Segment::G => 'g',
-        };
+        let letter = (b'a' + self.0) as char;
         f.write_str(&letter.to_string())
     }
 }
 
-#[derive(Debug)]
+#[derive(Debug, PartialEq, Eq)]
 enum SegmentError {
     InvalidName(char),
+    DuplicateName(char),
+    MissingName(char),
+    WrongCount { expected: usize, actual: usize },
+}
+
+impl std::fmt::Display for SegmentError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            SegmentError::InvalidName(c) => write!(f, "invalid segment {:?}", c),
+            SegmentError::DuplicateName(c) => write!(f, "segment {:?} appears twice", c),
+            SegmentError::MissingName(c) => write!(f, "segment {:?} is missing", c),
+            SegmentError::WrongCount { expected, actual } => {
+                write!(f, "expected {} segments, got {}", expected, actual)
+            }
+        }
+    }
 }
 
 impl TryFrom<char> for Segment {
     type Error = SegmentError;
 
     fn try_from(c: char) -> Result<Self, Self::Error> {
-        let segment = match c {
-            'a' => Segment::A,
-            'b' => Segment::B,
-            'c' => Segment::C,
-            'd' => Segment::D,
-            'e' => Segment::E,
-            'f' => Segment::F,
-            'g' => Segment::G,
-            c => return Err(SegmentError::InvalidName(c)),
-        };
+        if c.is_ascii_lowercase() && (c as u8 - b'a') < Segment::MAX {
+            Ok(Segment(c as u8 - b'a'))
+        } else {
+            Err(SegmentError::InvalidName(c))
+        }
+    }
+}
+
+/// The digits as in the puzzle, one symbol and its segments per line.
+const SEVEN_SEGMENT_DIGITS: &str = "\
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg";
+
+/// The digits followed by A, b, C, d, E and F.
+const SEVEN_SEGMENT_HEX: &str = "\
+0 abcefg
+1 cf
+2 acdeg
+3 acdfg
+4 bcdf
+5 abdfg
+6 abdefg
+7 acf
+8 abcdefg
+9 abcdfg
+A abcdef
+b bdefg
+C abeg
+d cdefg
+E abdeg
+F abde";
+
+/// Symbols a display can show and the segments lit for each of them. The
+/// index of a symbol is its value.
+#[derive(Debug)]
+struct GlyphTable {
+    symbols: Vec<char>,
+    glyphs: Vec<Vec<Segment>>,
+    /// Number of segments of the display.
+    segments: usize,
+}
+
+#[derive(Debug)]
+enum GlyphError {
+    InvalidLine(String),
+    InvalidSegment(SegmentError),
+    DuplicateGlyph(char, char),
+    TooManySymbols,
+}
+
+impl std::fmt::Display for GlyphError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            GlyphError::InvalidLine(line) => write!(f, "expected a symbol and its segments, got {:?}", line),
+            GlyphError::InvalidSegment(e) => write!(f, "{}", e),
+            GlyphError::DuplicateGlyph(a, b) => write!(f, "{} and {} have the same segments", a, b),
+            GlyphError::TooManySymbols => write!(f, "more than 256 symbols"),
+        }
+    }
+}
+
+impl FromStr for GlyphTable {
+    type Err = GlyphError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut symbols = Vec::new();
+        let mut glyphs: Vec<Vec<Segment>> = Vec::new();
+
+        for line in s.lines().filter(|line| !line.trim().is_empty()) {
+            let (symbol, segments) = match line.trim().split_once(' ') {
+                Some((symbol, segments)) if symbol.chars().count() == 1 => (symbol.chars().next().unwrap(), segments),
+                _ => return Err(GlyphError::InvalidLine(line.to_string())),
+            };
+            let mut glyph = Vec::new();
+            for c in segments.trim().chars() {
+                glyph.push(Segment::try_from(c).map_err(GlyphError::InvalidSegment)?);
+            }
+            glyph.sort_by_key(|segment| segment.0);
+            glyph.dedup();
+
+            if let Some(i) = glyphs.iter().position(|other| *other == glyph) {
+                return Err(GlyphError::DuplicateGlyph(symbols[i], symbol));
+            }
+            symbols.push(symbol);
+            glyphs.push(glyph);
+        }
+        if glyphs.len() > u8::MAX as usize + 1 {
+            return Err(GlyphError::TooManySymbols);
+        }
 
-        Ok(segment)
+        let segments = glyphs.iter().flatten().map(|segment| segment.0 as usize + 1).max().unwrap_or(0);
+        Ok(GlyphTable { symbols, glyphs, segments })
+    }
+}
+
+impl GlyphTable {
+    fn digits() -> GlyphTable {
+        SEVEN_SEGMENT_DIGITS.parse().unwrap()
+    }
+
+    fn hex() -> GlyphTable {
+        SEVEN_SEGMENT_HEX.parse().unwrap()
+    }
+
+    fn len(&self) -> usize {
+        self.glyphs.len()
+    }
+
+    fn symbols(&self) -> impl Iterator<Item = u8> {
+        0..self.len() as u8
+    }
+
+    fn glyph(&self, symbol: u8) -> &[Segment] {
+        &self.glyphs[symbol as usize]
+    }
+
+    fn segments(&self) -> impl Iterator<Item = Segment> {
+        Segment::members(self.segments)
+    }
+
+    /// The symbol which is the only one with `count` segments, like the 1,
+    /// 4, 7 and 8 of the digits.
+    fn unique_symbol(&self, count: usize) -> Option<u8> {
+        let mut symbols = self.symbols().filter(|&symbol| self.glyph(symbol).len() == count);
+        match (symbols.next(), symbols.next()) {
+            (Some(symbol), None) => Some(symbol),
+            _ => None,
+        }
     }
 }
 
@@ -150,19 +238,18 @@ impl FromStr for Pattern {
 }
 
 impl Pattern {
-    fn has_unique_digit_count(&self) -> bool {
-        let n = self.pattern.len();
-        n == 2 || n == 3 || n == 4 || n == 7
+    /// The pattern as bitmask, bit i for segment i. `None` if it uses a
+    /// segment beyond the seven of a `BitTable`.
+    fn mask(&self) -> Option<u8> {
+        self.pattern.iter().try_fold(0, |mask, segment| if segment.0 < 7 { Some(mask | 1 << segment.0) } else { None })
     }
 
-    fn get_unique_number(&self) -> Option<u8> {
-        match self.pattern.len() {
-            2 => Some(1),
-            3 => Some(7),
-            4 => Some(4),
-            7 => Some(8),
-            _ => None,
-        }
+    fn has_unique_digit_count(&self, table: &GlyphTable) -> bool {
+        self.get_unique_number(table).is_some()
+    }
+
+    fn get_unique_number(&self, table: &GlyphTable) -> Option<u8> {
+        table.unique_symbol(self.pattern.len())
     }
 }
 
@@ -179,6 +266,36 @@ impl Entry {
             .collect();
         patterns_strs[..].join(" ")
     }
+
+    /// Signal patterns and output value as bitmasks, see `Pattern::mask`.
+    fn masks(&self) -> Option<(Vec<u8>, Vec<u8>)> {
+        let patterns = self.patterns.iter().map(|pattern| pattern.mask()).collect::<Option<_>>()?;
+        let display = self.display.iter().map(|pattern| pattern.mask()).collect::<Option<_>>()?;
+        Some((patterns, display))
+    }
+}
+
+impl FromStr for Entry {
+    type Err = SegmentError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = s.split(" | ");
+        let patterns_str = parts.next().expect("Each line must contain patterns.");
+        let display_str = parts
+            .next()
+            .expect("Each line must contain a output value.");
+
+        let mut patterns: Vec<Pattern> = Vec::new();
+        for p in patterns_str.split(' ') {
+            patterns.push(Pattern::from_str(p)?);
+        }
+        let mut display: Vec<Pattern> = Vec::new();
+        for p in display_str.split(' ') {
+            display.push(Pattern::from_str(p)?);
+        }
+
+        Ok(Entry { patterns, display })
+    }
 }
 
 impl Debug for Entry {
@@ -200,22 +317,7 @@ fn read_input(filename: &str) -> Result<Vec<Entry>, SegmentError> {
 
     for line in reader.lines() {
         if let Ok(line) = line {
-            let mut parts = line.split(" | ");
-            let patterns_str = parts.next().expect("Each line must contain patterns.");
-            let display_str = parts
-                .next()
-                .expect("Each line must contain a output value.");
-
-            let mut patterns: Vec<Pattern> = Vec::new();
-            for p in patterns_str.split(' ') {
-                patterns.push(Pattern::from_str(p)?);
-            }
-            let mut display: Vec<Pattern> = Vec::new();
-            for p in display_str.split(' ') {
-                display.push(Pattern::from_str(p)?);
-            }
-
-            input.push(Entry { patterns, display });
+            input.push(line.parse()?);
         }
     }
 
@@ -223,13 +325,14 @@ fn read_input(filename: &str) -> Result<Vec<Entry>, SegmentError> {
 }
 
 fn part1(entries: &Vec<Entry>) -> usize {
+    let table = GlyphTable::digits();
     entries
         .iter()
         .map(|entry| {
             entry
                 .display
                 .iter()
-                .filter(|pattern| pattern.has_unique_digit_count())
+                .filter(|pattern| pattern.has_unique_digit_count(&table))
                 .count()
         })
         .sum()
@@ -250,27 +353,51 @@ impl<'a> SegmentStore<'a> {
         self.available_choices.push(choices);
     }
 
-    fn aquire_clear(&mut self, segment: &Segment) -> bool {
-        let mut index: Option<usize> = None;
-        for (i, segments) in self.available_choices.iter().enumerate() {
-            if segments.contains(segment) {
-                index = Some(i);
-            }
+    /// Whether each of the clear segments can be acquired from a different
+    /// choice, using up all choices. Taking just any fitting choice may block
+    /// a later segment, so this looks for a perfect matching by augmenting
+    /// paths.
+    fn aquire_all(&self, clears: &[Segment]) -> bool {
+        if clears.len() != self.available_choices.len() {
+            return false;
         }
 
-        if let Some(i) = index {
-            self.available_choices.remove(i);
-            true
-        } else {
-            false
-        }
+        let mut owners = vec![None; self.available_choices.len()];
+        (0..clears.len()).all(|i| {
+            let mut visited = vec![false; self.available_choices.len()];
+            self.augment(i, clears, &mut owners, &mut visited)
+        })
     }
 
-    fn remaining(&self) -> usize {
-        self.available_choices.len()
+    fn augment(&self, i: usize, clears: &[Segment], owners: &mut Vec<Option<usize>>, visited: &mut Vec<bool>) -> bool {
+        for (choice, segments) in self.available_choices.iter().enumerate() {
+            if visited[choice] || !segments.contains(&clears[i]) {
+                continue;
+            }
+            visited[choice] = true;
+            if owners[choice].is_none_or(|j| self.augment(j, clears, owners, visited)) {
+                owners[choice] = Some(i);
+                return true;
+            }
+        }
+        false
     }
 }
 
+/// A step of the deduction and the candidate table after it.
+#[derive(Debug)]
+struct Deduction {
+    reason: String,
+    candidates: String,
+}
+
+fn format_segments(segments: &[Segment]) -> String {
+    let mut segments = segments.to_vec();
+    segments.sort_by_key(|segment| segment.0);
+    let names: Vec<String> = segments.iter().map(|segment| format!("{:?}", segment)).collect();
+    format!("{{{}}}", names.join(","))
+}
+
 enum Rule {
     None,
     One(u8),
@@ -278,18 +405,19 @@ enum Rule {
 }
 
 #[derive(Debug)]
-struct Rules {
+struct Rules<'a> {
+    table: &'a GlyphTable,
     rules: HashMap<Segment, HashSet<Segment>>,
 }
 
-impl Rules {
-    fn new() -> Rules {
+impl<'a> Rules<'a> {
+    fn new(table: &'a GlyphTable) -> Rules<'a> {
         let rules = HashMap::new();
-        Rules { rules }
+        Rules { table, rules }
     }
 
     fn fill(&mut self) {
-        for segment in Segment::members() {
+        for segment in self.table.segments() {
             let segment_rules;
             match self.rules.get_mut(&segment) {
                 Some(r) => segment_rules = r,
@@ -299,14 +427,14 @@ impl Rules {
                     segment_rules = self.rules.get_mut(&segment).unwrap();
                 }
             }
-            for segment2 in Segment::members() {
+            for segment2 in self.table.segments() {
                 segment_rules.insert(segment2);
             }
         }
     }
 
-    fn full() -> Rules {
-        let mut rules = Rules::new();
+    fn full(table: &'a GlyphTable) -> Rules<'a> {
+        let mut rules = Rules::new(table);
         rules.fill();
         rules
     }
@@ -328,14 +456,32 @@ impl Rules {
         }
     }
 
-    fn generate_rules(patterns: &Vec<&Pattern>) -> Option<Rules> {
-        let unique_len_patterns = patterns.iter().filter(|p| p.has_unique_digit_count());
+    fn generate_rules(patterns: &Vec<&Pattern>, table: &'a GlyphTable) -> Option<Rules<'a>> {
+        Self::deduce(patterns, table, None)
+    }
+
+    /// Like `generate_rules`, but explain every deduction in `trace`.
+    fn generate_rules_traced(patterns: &Vec<&Pattern>, table: &'a GlyphTable, trace: &mut Vec<Deduction>) -> Option<Rules<'a>> {
+        Self::deduce(patterns, table, Some(trace))
+    }
+
+    fn deduce(patterns: &Vec<&Pattern>, table: &'a GlyphTable, mut trace: Option<&mut Vec<Deduction>>) -> Option<Rules<'a>> {
+        let unique_len_patterns = patterns.iter().filter(|p| p.has_unique_digit_count(table));
 
-        let mut rules = Rules::full();
+        let mut rules = Rules::full(table);
         // Add rules for known numbers
         for pattern in unique_len_patterns {
-            let number = pattern.get_unique_number().unwrap();
+            let number = pattern.get_unique_number(table).unwrap();
             rules.update_with_known_pattern(pattern, number);
+            rules.note(&mut trace, || {
+                format!(
+                    "pattern `{:?}` has {} segments, so it is {}, so {}",
+                    pattern,
+                    pattern.pattern.len(),
+                    table.symbols[number as usize],
+                    rules.explain_mapping(pattern, &[number])
+                )
+            });
         }
 
         // Iterate through all patterns and try to figure out additional rules from the allowed patterns.
@@ -351,6 +497,7 @@ impl Rules {
                         // Not finished and the last complete loop though the
                         // patterns did not further resolve the mapping. There
                         // is no point in trying again.
+                        rules.note(&mut trace, || "no pattern allows any further deduction, so the wiring stays unresolved".to_string());
                         return None;
                     }
                     iter = patterns.iter();
@@ -363,14 +510,34 @@ impl Rules {
             // number, we might extract more rules from it.
             match rules.test_pattern(pattern) {
                 Rule::One(number) => {
-                    changed |= rules.update_with_known_pattern(pattern, number);
+                    if rules.update_with_known_pattern(pattern, number) {
+                        changed = true;
+                        rules.note(&mut trace, || {
+                            format!(
+                                "pattern `{:?}` can only be {}, so {}",
+                                pattern,
+                                table.symbols[number as usize],
+                                rules.explain_mapping(pattern, &[number])
+                            )
+                        });
+                    }
                 }
                 Rule::Many(numbers) => {
-                    for number in numbers {
-                        changed |= rules.update_with_known_pattern(pattern, number);
+                    if rules.update_with_candidates(pattern, &numbers) {
+                        changed = true;
+                        rules.note(&mut trace, || {
+                            let symbols: Vec<String> = numbers.iter().map(|&n| table.symbols[n as usize].to_string()).collect();
+                            format!(
+                                "pattern `{:?}` can be {}, so {}",
+                                pattern,
+                                symbols.join(" or "),
+                                rules.explain_mapping(pattern, &numbers)
+                            )
+                        });
                     }
                 }
                 Rule::None => {
+                    rules.note(&mut trace, || format!("pattern `{:?}` cannot be any symbol with these candidates", pattern));
                     return None;
                 }
             }
@@ -379,27 +546,74 @@ impl Rules {
         Some(rules)
     }
 
+    /// Record a deduction together with the candidates after it.
+    fn note<F: FnOnce() -> String>(&self, trace: &mut Option<&mut Vec<Deduction>>, reason: F) {
+        if let Some(trace) = trace {
+            trace.push(Deduction { reason: reason(), candidates: self.format_candidates() });
+        }
+    }
+
+    /// Which clear segments the code segments of a pattern encoding one of
+    /// the numbers can light, and which the others can.
+    fn explain_mapping(&self, pattern: &Pattern, numbers: &[u8]) -> String {
+        let lit: Vec<Segment> = self
+            .table
+            .segments()
+            .filter(|clear| numbers.iter().any(|&number| self.table.glyph(number).contains(clear)))
+            .collect();
+        let dark: Vec<Segment> = self
+            .table
+            .segments()
+            .filter(|clear| numbers.iter().any(|&number| !self.table.glyph(number).contains(clear)))
+            .collect();
+        let others: Vec<Segment> = self.table.segments().filter(|code| !pattern.pattern.contains(code)).collect();
+
+        let mut explanation = format!("{} map to {}", format_segments(&pattern.pattern), format_segments(&lit));
+        if !others.is_empty() {
+            explanation += &format!(" and {} to {}", format_segments(&others), format_segments(&dark));
+        }
+        explanation
+    }
+
+    /// Candidate clear segments of each code segment, one per line.
+    fn format_candidates(&self) -> String {
+        let lines: Vec<String> = self
+            .table
+            .segments()
+            .map(|code| {
+                let mut clear: Vec<Segment> = self.rules[&code].iter().copied().collect();
+                clear.sort_by_key(|segment| segment.0);
+                let clear: Vec<String> = clear.iter().map(|segment| format!("{:?}", segment)).collect();
+                if clear.is_empty() {
+                    format!("{:?} -> none", code)
+                } else {
+                    format!("{:?} -> {}", code, clear.join(""))
+                }
+            })
+            .collect();
+        lines.join("\n")
+    }
+
     fn test_pattern(&self, pattern: &Pattern) -> Rule {
         let mut number_count: u8 = 0;
         let mut numbers: Vec<u8> = Vec::new();
 
         // Test if pattern matches to any number.
-        for number in 0u8..=9 {
+        for number in self.table.symbols() {
             let mut store = SegmentStore::new();
-            // Store all available clear segments.
+            // Store all available clear segments. Segments the display does
+            // not have cannot match anything.
             for code_segment in &pattern.pattern {
-                let clear_segments = self.rules.get(code_segment).unwrap();
-                store.add_clears(clear_segments);
+                match self.rules.get(code_segment) {
+                    Some(clear_segments) => store.add_clears(clear_segments),
+                    None => return Rule::None,
+                }
             }
 
-            // Remove clear segments required for number from the store
-            // one by one. If all clear segments are available and none
-            // remain, number can match.
-            if Segment::get_segments(number)
-                .iter()
-                .all(|s| store.aquire_clear(s))
-                && store.remaining() == 0
-            {
+            // Acquire the clear segments required for number from the
+            // store. If all clear segments are available and none remain,
+            // number can match.
+            if store.aquire_all(self.table.glyph(number)) {
                 number_count += 1;
                 numbers.push(number);
             }
@@ -412,20 +626,48 @@ impl Rules {
         }
     }
 
+    /// Keep only the connections which allow the pattern to encode at least
+    /// one of the given numbers. Applying `update_with_known_pattern` for
+    /// every number instead demands that the pattern encodes all of them at
+    /// once and removes connections of the true wiring.
+    fn update_with_candidates(&mut self, pattern: &Pattern, numbers: &[u8]) -> bool {
+        let mut changed = false;
+
+        for code_segment in self.table.segments() {
+            let lit = pattern.pattern.contains(&code_segment);
+            let allowed: HashSet<Segment> = self
+                .table
+                .segments()
+                .filter(|clear_segment| {
+                    numbers
+                        .iter()
+                        .any(|&number| self.table.glyph(number).contains(clear_segment) == lit)
+                })
+                .collect();
+
+            let possibilites = self.rules.get_mut(&code_segment).unwrap();
+            let before = possibilites.len();
+            possibilites.retain(|clear_segment| allowed.contains(clear_segment));
+            changed |= possibilites.len() != before;
+        }
+
+        changed
+    }
+
     fn update_with_known_pattern(&mut self, pattern: &Pattern, number: u8) -> bool {
         let mut changed = false;
 
         let pattern = &pattern.pattern;
         // println!("\nProcessing a {}: {:?}", number, pattern);
 
-        let segments = Segment::get_segments(number);
+        let segments = self.table.glyph(number);
         // println!("A {} must contain clear {:?}", number, segments);
-        for code_segment in Segment::members() {
+        for code_segment in self.table.segments() {
             let possibilites = self.rules.get_mut(&code_segment).unwrap();
             // println!("possibilities for {:?}: {:?}", code_segment, possibilites);
 
             if pattern.contains(&code_segment) {
-                for clear_segment in Segment::members() {
+                for clear_segment in self.table.segments() {
                     if !segments.contains(&clear_segment) {
                         // println!(
                         //     "remove {:?} from {:?}: {:?}",
@@ -435,7 +677,7 @@ impl Rules {
                     }
                 }
             } else {
-                for clear_segment in Segment::members() {
+                for clear_segment in self.table.segments() {
                     if segments.contains(&clear_segment) {
                         // println!(
                         //     "remove {:?} from {:?}: {:?}",
@@ -455,16 +697,687 @@ impl Rules {
     }
 }
 
+/// Clear segment lit by each code segment.
+#[derive(Clone, PartialEq, Eq)]
+struct Wiring {
+    clear: Vec<Segment>,
+}
+
+impl FromStr for Wiring {
+    type Err = SegmentError;
+
+    /// The clear segments lit by the code segments `a`, `b`, ... in order.
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut clear: Vec<Segment> = Vec::new();
+        for c in s.chars() {
+            let segment = Segment::try_from(c)?;
+            if clear.contains(&segment) {
+                return Err(SegmentError::DuplicateName(c));
+            }
+            clear.push(segment);
+        }
+        if let Some(missing) = Segment::members(clear.len()).find(|segment| !clear.contains(segment)) {
+            return Err(SegmentError::MissingName((b'a' + missing.0) as char));
+        }
+        Ok(Wiring { clear })
+    }
+}
+
+impl Wiring {
+    /// Parse a wiring of all segments of the display.
+    fn parse(s: &str, table: &GlyphTable) -> Result<Wiring, SegmentError> {
+        let wiring: Wiring = s.parse()?;
+        wiring.check(table)?;
+        Ok(wiring)
+    }
+
+    fn check(&self, table: &GlyphTable) -> Result<(), SegmentError> {
+        if self.clear.len() != table.segments {
+            return Err(SegmentError::WrongCount { expected: table.segments, actual: self.clear.len() });
+        }
+        Ok(())
+    }
+
+    fn random(segments: usize, rng: &mut Rng) -> Wiring {
+        let mut clear: Vec<Segment> = Segment::members(segments).collect();
+        rng.shuffle(&mut clear);
+        Wiring { clear }
+    }
+
+    /// Pattern lighting the glyph of a symbol, in random order.
+    fn encode(&self, symbol: u8, table: &GlyphTable, rng: &mut Rng) -> Pattern {
+        let mut pattern: Vec<Segment> = table
+            .glyph(symbol)
+            .iter()
+            .map(|clear| {
+                let code = self.clear.iter().position(|segment| segment == clear);
+                Segment(code.expect("Wiring does not fit the display") as u8)
+            })
+            .collect();
+        rng.shuffle(&mut pattern);
+        Pattern { pattern }
+    }
+
+    fn decode(&self, pattern: &Pattern, table: &GlyphTable) -> Option<u8> {
+        let mut lit = Vec::new();
+        for segment in &pattern.pattern {
+            lit.push(*self.clear.get(segment.0 as usize)?);
+        }
+        table
+            .symbols()
+            .find(|&symbol| {
+                let glyph = table.glyph(symbol);
+                glyph.len() == lit.len() && lit.iter().all(|segment| glyph.contains(segment))
+            })
+    }
+}
+
+impl Debug for Wiring {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs: Vec<String> = self
+            .clear
+            .iter()
+            .enumerate()
+            .map(|(code, clear)| format!("{:?}->{:?}", Segment(code as u8), clear))
+            .collect();
+        f.write_str(&pairs.join(" "))
+    }
+}
+
+/// Inverse of decoding: an entry with the patterns of all symbols in random
+/// order, followed by the given output value.
+fn encode_entry(value: &[u8], wiring: &Wiring, table: &GlyphTable, rng: &mut Rng) -> Result<Entry, SegmentError> {
+    wiring.check(table)?;
+    let mut patterns: Vec<Pattern> = table.symbols().map(|symbol| wiring.encode(symbol, table, rng)).collect();
+    rng.shuffle(&mut patterns);
+    let display = value.iter().map(|&symbol| wiring.encode(symbol, table, rng)).collect();
+
+    Ok(Entry { patterns, display })
+}
+
+/// Random entries with four symbols each and their output values. Every entry
+/// has a random wiring unless one is given.
+fn generate_entries(
+    count: usize,
+    table: &GlyphTable,
+    wiring: Option<&Wiring>,
+    seed: u64,
+) -> Result<Vec<(Entry, Vec<u8>)>, SegmentError> {
+    let mut rng = Rng::new(seed);
+    (0..count)
+        .map(|_| {
+            let value: Vec<u8> = (0..4).map(|_| rng.below(table.len()) as u8).collect();
+            let entry = match wiring {
+                Some(wiring) => encode_entry(&value, wiring, table, &mut rng)?,
+                None => {
+                    let wiring = Wiring::random(table.segments, &mut rng);
+                    encode_entry(&value, &wiring, table, &mut rng)?
+                }
+            };
+            Ok((entry, value))
+        })
+        .collect()
+}
+
+/// All wirings which explain the patterns of an entry, or as many as the
+/// search was allowed to find.
+#[derive(Debug)]
+struct Analysis {
+    wirings: Vec<Wiring>,
+    /// The symbols each pattern of the output value decodes to across the
+    /// wirings.
+    display: Vec<BTreeSet<u8>>,
+    /// Whether the wirings are all there are. If not, `display` only covers
+    /// the wirings found.
+    complete: bool,
+}
+
+impl Analysis {
+    /// Positions of the output value showing different symbols depending on
+    /// the wiring.
+    fn ambiguous(&self) -> Vec<usize> {
+        (0..self.display.len()).filter(|&i| self.display[i].len() > 1).collect()
+    }
+
+    /// The output value, if it is the same for all wirings.
+    fn value(&self) -> Option<Vec<u8>> {
+        self.display
+            .iter()
+            .map(|symbols| if symbols.len() == 1 { symbols.iter().next().copied() } else { None })
+            .collect()
+    }
+}
+
+#[derive(Debug, PartialEq, Eq)]
+enum WiringError {
+    /// No wiring explains all patterns. The conflicting patterns have no
+    /// consistent wiring, but without any single one of them there is one.
+    NoSolution { conflicting: Vec<String> },
+}
+
+/// Wirings `solve_entry` looks for before giving up on finding them all.
+const WIRING_LIMIT: usize = 100_000;
+
+/// Search for every wiring consistent with the patterns, without assuming
+/// that there is exactly one. The candidates are narrowed down by the number
+/// of segments of each pattern first, then a backtracking search assigns one
+/// code segment after the other. Underdetermined entries may have up to n!
+/// wirings for a display with n segments, the search stops after `limit`.
+fn find_wirings(patterns: &[&Pattern], table: &GlyphTable, limit: usize) -> Vec<Wiring> {
+    let mut rules = Rules::full(table);
+    let mut candidates = Vec::new();
+    for pattern in patterns {
+        let symbols: Vec<u8> = table
+            .symbols()
+            .filter(|&symbol| table.glyph(symbol).len() == pattern.pattern.len())
+            .collect();
+        if symbols.is_empty() || pattern.pattern.iter().any(|segment| segment.0 as usize >= table.segments) {
+            return Vec::new();
+        }
+        rules.update_with_candidates(pattern, &symbols);
+        candidates.push(symbols);
+    }
+
+    // Assign the most constrained code segments first.
+    let mut order: Vec<Segment> = table.segments().collect();
+    order.sort_by_key(|segment| rules.rules[segment].len());
+
+    let mut search = WiringSearch {
+        patterns,
+        candidates: &candidates,
+        rules: &rules,
+        order: &order,
+        clear: vec![None; table.segments],
+        wirings: Vec::new(),
+        limit,
+    };
+    search.assign(0);
+    search.wirings
+}
+
+struct WiringSearch<'a> {
+    patterns: &'a [&'a Pattern],
+    candidates: &'a [Vec<u8>],
+    rules: &'a Rules<'a>,
+    order: &'a [Segment],
+    clear: Vec<Option<Segment>>,
+    wirings: Vec<Wiring>,
+    limit: usize,
+}
+
+impl<'a> WiringSearch<'a> {
+    fn assign(&mut self, depth: usize) {
+        if depth == self.order.len() {
+            let clear = self.clear.iter().map(|segment| segment.unwrap()).collect();
+            self.wirings.push(Wiring { clear });
+            return;
+        }
+
+        let code = self.order[depth];
+        let mut choices: Vec<Segment> = self.rules.rules[&code].iter().copied().collect();
+        choices.sort_by_key(|segment| segment.0);
+        for choice in choices {
+            if self.wirings.len() >= self.limit {
+                break;
+            }
+            if self.clear.contains(&Some(choice)) {
+                continue;
+            }
+            self.clear[code.0 as usize] = Some(choice);
+            if self.is_consistent() {
+                self.assign(depth + 1);
+            }
+        }
+        self.clear[code.0 as usize] = None;
+    }
+
+    /// Whether every pattern can still become one of its candidate symbols:
+    /// the assigned code segments light exactly the glyph's segments.
+    fn is_consistent(&self) -> bool {
+        let table = self.rules.table;
+        self.patterns.iter().zip(self.candidates).all(|(pattern, symbols)| {
+            symbols.iter().any(|&symbol| {
+                let glyph = table.glyph(symbol);
+                self.clear.iter().enumerate().all(|(code, clear)| match clear {
+                    Some(clear) => pattern.pattern.contains(&Segment(code as u8)) == glyph.contains(clear),
+                    None => true,
+                })
+            })
+        })
+    }
+}
+
+/// Find up to `limit` wirings of an entry and what its output value may be.
+/// If there is none, narrow the patterns down to a set which conflicts on its
+/// own.
+fn solve_entry(entry: &Entry, table: &GlyphTable, limit: usize) -> Result<Analysis, WiringError> {
+    let patterns: Vec<&Pattern> = entry.patterns.iter().chain(entry.display.iter()).collect();
+    let mut wirings = find_wirings(&patterns, table, limit.saturating_add(1));
+    let complete = wirings.len() <= limit;
+    wirings.truncate(limit);
+
+    if wirings.is_empty() {
+        let mut conflicting = patterns;
+        let mut i = 0;
+        while i < conflicting.len() {
+            let mut without = conflicting.clone();
+            without.remove(i);
+            if find_wirings(&without, table, 1).is_empty() {
+                conflicting = without;
+            } else {
+                i += 1;
+            }
+        }
+        let conflicting = conflicting.iter().map(|pattern| format!("{:?}", pattern)).collect();
+        return Err(WiringError::NoSolution { conflicting });
+    }
+
+    let display = entry
+        .display
+        .iter()
+        .map(|pattern| {
+            wirings
+                .iter()
+                .map(|wiring| wiring.decode(pattern, table).expect("Wirings decode all patterns"))
+                .collect()
+        })
+        .collect();
+
+    Ok(Analysis { wirings, display, complete })
+}
+
+/// Glyph table of a seven segment display as bitmasks.
+struct BitTable {
+    glyphs: Vec<u8>,
+    /// The symbol shown by each of the 128 bitmasks.
+    symbols: [Option<u8>; 128],
+}
+
+impl BitTable {
+    /// `None` for displays with more than seven segments.
+    fn new(table: &GlyphTable) -> Option<BitTable> {
+        if table.segments > 7 {
+            return None;
+        }
+        let glyphs: Vec<u8> = table
+            .symbols()
+            .map(|symbol| table.glyph(symbol).iter().fold(0, |mask, segment| mask | 1 << segment.0))
+            .collect();
+        let mut symbols = [None; 128];
+        for (symbol, &glyph) in glyphs.iter().enumerate() {
+            symbols[glyph as usize] = Some(symbol as u8);
+        }
+        Some(BitTable { glyphs, symbols })
+    }
+}
+
+/// Candidate wiring as a 7×7 bit matrix: bit j of row i is set if code
+/// segment i may light clear segment j. It is the bitmask version of `Rules`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct BitRules {
+    rows: [u8; 7],
+}
+
+impl BitRules {
+    const ALL: u8 = 0x7f;
+
+    fn full() -> BitRules {
+        BitRules { rows: [Self::ALL; 7] }
+    }
+
+    fn is_resolved(&self) -> bool {
+        self.rows.iter().all(|row| row.count_ones() == 1)
+    }
+
+    /// Whether the pattern may encode the glyph, checking each code segment
+    /// on its own. Only the `healthy` code segments are known to show the
+    /// glyph, the others may be broken.
+    fn allows(&self, pattern: u8, glyph: u8, healthy: u8) -> bool {
+        let lit = (pattern & healthy).count_ones();
+        let broken = (!healthy & Self::ALL).count_ones();
+        lit <= glyph.count_ones()
+            && glyph.count_ones() <= lit + broken
+            && (0..7).filter(|i| healthy & 1 << i != 0).all(|i| {
+                let clear = if pattern & 1 << i != 0 { glyph } else { !glyph & Self::ALL };
+                self.rows[i] & clear != 0
+            })
+    }
+
+    /// Keep only the connections of the `healthy` code segments which allow
+    /// the pattern to encode at least one of the glyphs. Like
+    /// `Rules::update_with_candidates`.
+    fn restrict(&mut self, pattern: u8, glyphs: &[u8], healthy: u8) -> bool {
+        let lit = glyphs.iter().fold(0, |mask, glyph| mask | glyph);
+        let dark = glyphs.iter().fold(0, |mask, glyph| mask | (!glyph & Self::ALL));
+
+        let before = self.rows;
+        for i in (0..7).filter(|i| healthy & 1 << i != 0) {
+            self.rows[i] &= if pattern & 1 << i != 0 { lit } else { dark };
+        }
+        self.rows != before
+    }
+
+    /// Clear segments lit by a pattern, once resolved.
+    fn apply(&self, pattern: u8) -> u8 {
+        (0..7).filter(|i| pattern & 1 << i != 0).fold(0, |mask, i| mask | self.rows[i])
+    }
+}
+
+/// The only wiring allowed by the rules which maps every pattern to a glyph,
+/// if there is exactly one.
+fn unique_bit_wiring(rules: &BitRules, patterns: &[u8], display: &[u8], table: &BitTable) -> Option<BitRules> {
+    struct Search<'a> {
+        patterns: &'a [u8],
+        display: &'a [u8],
+        table: &'a BitTable,
+        found: Option<BitRules>,
+        count: usize,
+    }
+
+    impl<'a> Search<'a> {
+        fn assign(&mut self, rules: &mut BitRules, row: usize, used: u8) {
+            if self.count > 1 {
+                return;
+            }
+            if row == 7 {
+                let mut all = self.patterns.iter().chain(self.display);
+                if all.all(|&pattern| self.table.symbols[rules.apply(pattern) as usize].is_some()) {
+                    self.found = Some(*rules);
+                    self.count += 1;
+                }
+                return;
+            }
+            let choices = rules.rows[row] & !used;
+            for j in (0..7).filter(|j| choices & 1 << j != 0) {
+                let saved = rules.rows[row];
+                rules.rows[row] = 1 << j;
+                self.assign(rules, row + 1, used | 1 << j);
+                rules.rows[row] = saved;
+            }
+        }
+    }
+
+    let mut search = Search { patterns, display, table, found: None, count: 0 };
+    search.assign(&mut rules.clone(), 0, 0);
+    if search.count == 1 {
+        search.found
+    } else {
+        None
+    }
+}
+
+/// Counterpart of `decode_entry` on bitmasks for seven segment displays. The
+/// rules are narrowed down by the glyphs each pattern still allows until
+/// nothing changes. The wirings left, usually only one, are then checked
+/// against all patterns.
+fn decode_entry_fast(patterns: &[u8], display: &[u8], table: &BitTable) -> Option<Vec<u8>> {
+    let mut rules = BitRules::full();
+    let mut candidates = [0u8; 128];
+
+    let mut changed = true;
+    while changed && !rules.is_resolved() {
+        changed = false;
+        for &pattern in patterns.iter().chain(display) {
+            let mut n = 0;
+            for &glyph in &table.glyphs {
+                if rules.allows(pattern, glyph, BitRules::ALL) {
+                    candidates[n] = glyph;
+                    n += 1;
+                }
+            }
+            if n == 0 {
+                return None;
+            }
+            changed |= rules.restrict(pattern, &candidates[..n], BitRules::ALL);
+        }
+    }
+
+    let rules = unique_bit_wiring(&rules, patterns, display, table)?;
+    display.iter().map(|&pattern| table.symbols[rules.apply(pattern) as usize]).collect()
+}
+
+/// Broken wires of a display as bitmasks of code segments. A wire stuck on is
+/// lit in every pattern, one stuck off in none.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Faults {
+    stuck_on: u8,
+    stuck_off: u8,
+}
+
+impl Faults {
+    fn count(&self) -> u32 {
+        (self.stuck_on | self.stuck_off).count_ones()
+    }
+}
+
+/// Most likely reading of an entry on a display with broken wires.
+#[derive(Debug)]
+struct FaultyDecoding {
+    faults: Faults,
+    wiring: BitRules,
+    value: Vec<u8>,
+    /// Number of wirings and fault sets with the fewest faults explaining all
+    /// patterns.
+    explanations: usize,
+    /// Share of the readings of these explanations which give `value`. A
+    /// broken wire can hide the difference between two symbols.
+    confidence: f64,
+}
+
+/// Whether every pattern can be given a different one of its candidate
+/// symbols, by augmenting paths like `SegmentStore::aquire_all`.
+fn assign_symbols(candidates: &[Vec<usize>], symbols: usize) -> bool {
+    fn augment(i: usize, candidates: &[Vec<usize>], owners: &mut Vec<Option<usize>>, visited: &mut Vec<bool>) -> bool {
+        for &symbol in &candidates[i] {
+            if visited[symbol] {
+                continue;
+            }
+            visited[symbol] = true;
+            if owners[symbol].is_none_or(|j| augment(j, candidates, owners, visited)) {
+                owners[symbol] = Some(i);
+                return true;
+            }
+        }
+        false
+    }
+
+    let mut owners = vec![None; symbols];
+    (0..candidates.len()).all(|i| augment(i, candidates, &mut owners, &mut vec![false; symbols]))
+}
+
+/// Every wiring which, given the faults, explains all patterns. On the
+/// healthy wires a pattern must show exactly a glyph, the broken ones tell
+/// nothing. The signal patterns still stand for different symbols. Like
+/// `decode_entry_fast`, the rules are narrowed down first, then the search
+/// drops a partial wiring as soon as a pattern fits no glyph.
+fn faulty_wirings(patterns: &[u8], display: &[u8], faults: &Faults, table: &BitTable) -> Vec<BitRules> {
+    struct Search<'a> {
+        patterns: &'a [u8],
+        display: &'a [u8],
+        table: &'a BitTable,
+        healthy: u8,
+        found: Vec<BitRules>,
+    }
+
+    impl<'a> Search<'a> {
+        /// Symbols the pattern may show, judged by the healthy code segments
+        /// among the first `rows`, whose rows are resolved.
+        fn candidates(&self, rules: &BitRules, rows: usize, pattern: u8) -> Vec<usize> {
+            let known = self.healthy & ((1u8 << rows) - 1);
+            let visible = rules.apply(known);
+            let shown = rules.apply(pattern & known);
+            (0..self.table.glyphs.len()).filter(|&symbol| self.table.glyphs[symbol] & visible == shown).collect()
+        }
+
+        fn assign(&mut self, rules: &mut BitRules, row: usize, used: u8) {
+            if row == 7 {
+                let signals: Vec<Vec<usize>> =
+                    self.patterns.iter().map(|&pattern| self.candidates(rules, 7, pattern)).collect();
+                if assign_symbols(&signals, self.table.glyphs.len()) {
+                    self.found.push(*rules);
+                }
+                return;
+            }
+            let choices = rules.rows[row] & !used;
+            for j in (0..7).filter(|j| choices & 1 << j != 0) {
+                let saved = rules.rows[row];
+                rules.rows[row] = 1 << j;
+                let mut all = self.patterns.iter().chain(self.display);
+                if all.all(|&pattern| !self.candidates(rules, row + 1, pattern).is_empty()) {
+                    self.assign(rules, row + 1, used | 1 << j);
+                }
+                rules.rows[row] = saved;
+            }
+        }
+    }
+
+    let healthy = BitRules::ALL & !(faults.stuck_on | faults.stuck_off);
+    let mut rules = BitRules::full();
+    let mut changed = true;
+    while changed {
+        changed = false;
+        for &pattern in patterns.iter().chain(display) {
+            let glyphs: Vec<u8> =
+                table.glyphs.iter().copied().filter(|&glyph| rules.allows(pattern, glyph, healthy)).collect();
+            if glyphs.is_empty() {
+                return Vec::new();
+            }
+            changed |= rules.restrict(pattern, &glyphs, healthy);
+        }
+    }
+
+    let mut search = Search { patterns, display, table, healthy, found: Vec::new() };
+    search.assign(&mut rules, 0, 0);
+    search.found
+}
+
+/// Decode an entry of a seven segment display with up to `max_faults` broken
+/// wires. The fault sets are tried by increasing size, and all wirings for
+/// the smallest size that explains the patterns are considered. `None` if
+/// nothing explains them.
+fn decode_with_faults(patterns: &[u8], display: &[u8], table: &BitTable, max_faults: u32) -> Option<FaultyDecoding> {
+    let all: Vec<u8> = patterns.iter().chain(display).copied().collect();
+    let always = all.iter().fold(BitRules::ALL, |mask, &pattern| mask & pattern);
+    let never = all.iter().fold(BitRules::ALL, |mask, &pattern| mask & !pattern);
+    let suspects = always | never;
+
+    for count in 0..=max_faults {
+        let mut explanations = Vec::new();
+        for broken in (0..=BitRules::ALL).filter(|&broken| broken & !suspects == 0 && broken.count_ones() == count) {
+            let faults = Faults { stuck_on: broken & always, stuck_off: broken & never & !always };
+            for wiring in faulty_wirings(patterns, display, &faults, table) {
+                explanations.push((faults, wiring));
+            }
+        }
+        if explanations.is_empty() {
+            continue;
+        }
+
+        // Count the readings of the output value over all explanations.
+        let mut readings: HashMap<Vec<u8>, (usize, Faults, BitRules)> = HashMap::new();
+        let mut total = 0;
+        for &(faults, wiring) in &explanations {
+            let healthy = BitRules::ALL & !(faults.stuck_on | faults.stuck_off);
+            let visible = wiring.apply(healthy);
+            let mut values: Vec<Vec<u8>> = vec![Vec::new()];
+            for &pattern in display {
+                let shown = wiring.apply(pattern & healthy);
+                let symbols: Vec<u8> = (0..table.glyphs.len() as u8)
+                    .filter(|&symbol| table.glyphs[symbol as usize] & visible == shown)
+                    .collect();
+                values = values
+                    .iter()
+                    .flat_map(|value| symbols.iter().map(move |&symbol| [value.as_slice(), &[symbol]].concat()))
+                    .collect();
+            }
+            for value in values {
+                total += 1;
+                readings.entry(value).or_insert((0, faults, wiring)).0 += 1;
+            }
+        }
+
+        let (value, (count, faults, wiring)) = readings
+            .into_iter()
+            .max_by(|(a, (m, _, _)), (b, (n, _, _))| m.cmp(n).then_with(|| b.cmp(a)))?;
+        return Some(FaultyDecoding {
+            faults,
+            wiring,
+            value,
+            explanations: explanations.len(),
+            confidence: count as f64 / total as f64,
+        });
+    }
+
+    None
+}
+
+/// Like `part2`, but `None` if an entry does not fit a seven segment display
+/// or cannot be resolved.
+fn part2_fast(entries: &[Entry]) -> Option<u64> {
+    let table = BitTable::new(&GlyphTable::digits())?;
+    let mut total: u64 = 0;
+    for entry in entries {
+        let (patterns, display) = entry.masks()?;
+        let numbers = decode_entry_fast(&patterns, &display, &table)?;
+        total += numbers.iter().fold(0, |current, &number| current * 10 + number as u64);
+    }
+
+    Some(total)
+}
+
+/// The deductions made for an entry, the candidates after each of them and
+/// the final mapping and output value, or why the entry was rejected.
+fn explain_entry(entry: &Entry, table: &GlyphTable) -> String {
+    let patterns: Vec<&Pattern> = entry.patterns.iter().chain(entry.display.iter()).collect();
+    let mut trace = Vec::new();
+    let rules = Rules::generate_rules_traced(&patterns, table, &mut trace);
+
+    let mut explanation = format!("{:?}\n", entry);
+    for (i, deduction) in trace.iter().enumerate() {
+        explanation += &format!("{}. {}\n", i + 1, deduction.reason);
+        for line in deduction.candidates.lines() {
+            explanation += &format!("     {}\n", line);
+        }
+    }
+
+    let rules = match rules {
+        Some(rules) => rules,
+        None => return explanation + "Rejected: the wiring cannot be resolved.\n",
+    };
+    let mapping: Vec<String> = table
+        .segments()
+        .map(|code| format!("{:?}->{:?}", code, rules.rules[&code].iter().next().unwrap()))
+        .collect();
+    explanation += &format!("Final mapping: {}\n", mapping.join(" "));
+
+    let shown: Option<String> = entry
+        .display
+        .iter()
+        .map(|pattern| rules.resolve(pattern).map(|symbol| table.symbols[symbol as usize]))
+        .collect();
+    match shown {
+        Some(shown) => explanation + &format!("Output value: {}\n", shown),
+        None => explanation + "Rejected: the output value shows an unknown pattern.\n",
+    }
+}
+
+/// Symbols shown on the display of an entry, if the wiring can be resolved.
+fn decode_entry(entry: &Entry, table: &GlyphTable) -> Option<Vec<u8>> {
+    let patterns: Vec<&Pattern> = entry.patterns.iter().chain(entry.display.iter()).collect();
+    let rule = Rules::generate_rules(&patterns, table)?;
+
+    entry.display.iter().map(|pattern| rule.resolve(pattern)).collect()
+}
+
 fn part2(entries: &Vec<Entry>) -> u32 {
+    let table = GlyphTable::digits();
     let mut total: u32 = 0;
     for entry in entries {
-        let patterns: Vec<&Pattern> = entry.patterns.iter().chain(entry.display.iter()).collect();
-        let rule = Rules::generate_rules(&patterns).expect("Unable to resolve the mapping.");
+        let numbers = decode_entry(entry, &table).expect("Unable to resolve the mapping.");
         let mut current: u32 = 0;
 
         // print!("{:?} -> ", entry.display);
-        for pattern in &entry.display {
-            let number = rule.resolve(pattern).unwrap();
+        for number in numbers {
             // print!("{}", number);
 
             current = current * 10 + number as u32;
@@ -485,6 +1398,170 @@ fn main() {
     //     println!("{:?}", entry);
     // }
 
+    // Pass "hex <file>" or "glyphs <table file> <file>" to decode the
+    // entries of another display. A table has one symbol and its segments
+    // per line, e.g. "1 cf". Pass "wirings <file>" to list how many wirings
+    // explain each entry and which digits are ambiguous, "fast <file>" to
+    // solve part 2 for the file with bitmasks or "explain <file>" to show
+    // how each entry is decoded. Pass "generate <count> <seed> <file>
+    // [wiring]" to write random entries and print the answers for them; a
+    // wiring like "cfgabde" lists the segments lit by a, b, ... in order.
+    // Pass "faults <k> <file> [table]" to decode displays with up to k broken
+    // wires, optionally with a glyph table of at most seven segments.
+    let args: Vec<String> = std::env::args().skip(1).collect();
+    if args.first().map(|arg| arg.as_str()) == Some("faults") {
+        let max_faults = args.get(1).expect("Missing number of faults").parse().expect("Cannot parse number of faults");
+        let input = read_input(args.get(2).expect("Missing input file")).unwrap();
+        let glyphs = match args.get(3) {
+            Some(filename) => {
+                let table = std::fs::read_to_string(filename).expect("Cannot open file");
+                table.parse().unwrap_or_else(|e| panic!("Invalid glyph table: {}", e))
+            }
+            None => GlyphTable::digits(),
+        };
+        let table = match BitTable::new(&glyphs) {
+            Some(table) => table,
+            None => {
+                println!("Broken wires are only found on displays with at most seven segments");
+                return;
+            }
+        };
+        let wires = |mask: u8| -> String { (0..7).filter(|i| mask & 1 << i != 0).map(|i| (b'a' + i) as char).collect() };
+        for entry in &input {
+            let (patterns, display) = match entry.masks() {
+                Some(masks) => masks,
+                None => {
+                    println!("{:?} -> uses segments beyond g", entry);
+                    continue;
+                }
+            };
+            match decode_with_faults(&patterns, &display, &table, max_faults) {
+                Some(decoding) => {
+                    let value: String = decoding.value.iter().map(|&symbol| glyphs.symbols[symbol as usize]).collect();
+                    let wiring: String = decoding.wiring.rows.iter().map(|row| wires(*row)).collect();
+                    println!(
+                        "{:?} -> {} with {:.0}% confidence, wiring {} (one of {}), {} faults, stuck on: {:?}, stuck off: {:?}",
+                        entry,
+                        value,
+                        100.0 * decoding.confidence,
+                        wiring,
+                        decoding.explanations,
+                        decoding.faults.count(),
+                        wires(decoding.faults.stuck_on),
+                        wires(decoding.faults.stuck_off)
+                    );
+                }
+                None => println!("{:?} -> more than {} broken wires", entry, max_faults),
+            }
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("generate") {
+        let count = args.get(1).expect("Missing number of entries").parse().expect("Cannot parse number of entries");
+        let seed = args.get(2).expect("Missing seed").parse().expect("Cannot parse seed");
+        let filename = args.get(3).expect("Missing output file");
+        let table = GlyphTable::digits();
+        let wiring = match args.get(4) {
+            Some(wiring) => match Wiring::parse(wiring, &table) {
+                Ok(wiring) => Some(wiring),
+                Err(e) => {
+                    println!("Invalid wiring: {}", e);
+                    return;
+                }
+            },
+            None => None,
+        };
+
+        let entries = generate_entries(count, &table, wiring.as_ref(), seed).unwrap();
+        let lines: Vec<String> = entries.iter().map(|(entry, _)| format!("{:?}\n", entry)).collect();
+        std::fs::write(filename, lines.concat()).expect("Cannot write file");
+
+        let unique = entries
+            .iter()
+            .flat_map(|(_, value)| value.iter())
+            .filter(|&&symbol| table.unique_symbol(table.glyph(symbol).len()).is_some())
+            .count();
+        let total: u64 = entries
+            .iter()
+            .map(|(_, value)| value.iter().fold(0, |current, &number| current * 10 + number as u64))
+            .sum();
+        println!("Part 1: {}", unique);
+        println!("Part 2: {}", total);
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("explain") {
+        let table = GlyphTable::digits();
+        let input = read_input(args.get(1).expect("Missing input file")).unwrap();
+        for entry in &input {
+            println!("{}", explain_entry(entry, &table));
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("fast") {
+        let input = read_input(args.get(1).expect("Missing input file")).unwrap();
+        match part2_fast(&input) {
+            Some(total) => println!("Part 2: {}", total),
+            None => println!("Cannot decode all entries with bitmasks"),
+        }
+        return;
+    }
+    if args.first().map(|arg| arg.as_str()) == Some("wirings") {
+        let table = GlyphTable::digits();
+        let input = read_input(args.get(1).expect("Missing input file")).unwrap();
+        for entry in &input {
+            match solve_entry(entry, &table, WIRING_LIMIT) {
+                Ok(analysis) => {
+                    let wirings = if analysis.complete {
+                        format!("{} wirings", analysis.wirings.len())
+                    } else {
+                        format!("search stopped after {} wirings", analysis.wirings.len())
+                    };
+                    match analysis.value() {
+                        Some(value) => {
+                            let shown: String = value.iter().map(|symbol| symbol.to_string()).collect();
+                            println!("{:?} -> {} ({})", entry, shown, wirings);
+                        }
+                        None => {
+                            let ambiguous: Vec<String> = analysis
+                                .ambiguous()
+                                .iter()
+                                .map(|&i| format!("digit {} is one of {:?}", i + 1, analysis.display[i]))
+                                .collect();
+                            println!("{:?} -> ambiguous, {} ({})", entry, ambiguous.join(", "), wirings);
+                        }
+                    }
+                }
+                Err(WiringError::NoSolution { conflicting }) => {
+                    println!("{:?} -> no solution, conflicting: {}", entry, conflicting.join(" "));
+                }
+            }
+        }
+        return;
+    }
+    let table = match args.first().map(|arg| arg.as_str()) {
+        Some("hex") => Some((GlyphTable::hex(), args.get(1))),
+        Some("glyphs") => {
+            let filename = args.get(1).expect("Missing glyph table");
+            let table = std::fs::read_to_string(filename).expect("Cannot open file");
+            let table = table.parse().unwrap_or_else(|e| panic!("Invalid glyph table: {}", e));
+            Some((table, args.get(2)))
+        }
+        _ => None,
+    };
+    if let Some((table, filename)) = table {
+        let input = read_input(filename.expect("Missing input file")).unwrap();
+        for entry in &input {
+            match decode_entry(entry, &table) {
+                Some(symbols) => {
+                    let shown: String = symbols.iter().map(|&symbol| table.symbols[symbol as usize]).collect();
+                    println!("{:?} -> {}", entry, shown);
+                }
+                None => println!("{:?} -> unresolved", entry),
+            }
+        }
+        return;
+    }
+
     println!("Part 1: {}", part1(&input));
     println!("Part 2: {}", part2(&input));
 }
@@ -524,4 +1601,291 @@ mod tests08 {
 
         assert_eq!(solution, 61229);
     }
+
+    #[test]
+    fn test_glyph_tables() {
+        let digits = GlyphTable::digits();
+        let unique: Vec<Option<u8>> = (0..=7).map(|count| digits.unique_symbol(count)).collect();
+        assert_eq!(unique, vec![None, None, Some(1), Some(7), Some(4), None, None, Some(8)]);
+
+        // Among the hex digits, C and F have as many segments as 4.
+        let hex = GlyphTable::hex();
+        assert_eq!((hex.len(), hex.segments), (16, 7));
+        assert_eq!(hex.unique_symbol(4), None);
+
+        let input = read_input("test_inputs/08_03.txt").unwrap();
+        let decoded: Vec<Option<Vec<u8>>> = input.iter().map(|entry| decode_entry(entry, &hex)).collect();
+        assert_eq!(
+            decoded,
+            vec![Some(vec![3, 0, 12, 10]), Some(vec![12, 12, 11, 11]), Some(vec![11, 5, 9, 8])]
+        );
+        assert!(decode_entry(&input[0], &digits).is_none());
+
+        assert!(matches!("0 abc\n1 cba".parse::<GlyphTable>(), Err(GlyphError::DuplicateGlyph('0', '1'))));
+        assert!(matches!("0 abz".parse::<GlyphTable>(), Err(GlyphError::InvalidSegment(_))));
+        assert!(matches!("abc".parse::<GlyphTable>(), Err(GlyphError::InvalidLine(_))));
+    }
+
+    #[test]
+    fn test_decimal_point() {
+        // The digits and a decimal point on an eighth segment.
+        let table: GlyphTable = format!("{}\n. h", SEVEN_SEGMENT_DIGITS).parse().unwrap();
+        assert_eq!(table.unique_symbol(1), Some(10));
+
+        let input = read_input("test_inputs/08_04.txt").unwrap();
+        assert_eq!(decode_entry(&input[0], &table), Some(vec![2, 8, 0, 1, 8]));
+        assert_eq!(decode_entry(&input[1], &table), Some(vec![5, 2, 9, 4, 1]));
+    }
+
+    #[test]
+    fn test_all_wirings() {
+        let table = GlyphTable::digits();
+        let input = read_input("test_inputs/08_02.txt").unwrap();
+        for entry in &input {
+            let analysis = solve_entry(entry, &table, WIRING_LIMIT).unwrap();
+            assert_eq!(analysis.wirings.len(), 1);
+            assert_eq!(analysis.value(), decode_entry(entry, &table));
+        }
+
+        // A one is lit by c and f in either order, all other segments are free.
+        let entry: Entry = "ab | ab ba".parse().unwrap();
+        let analysis = solve_entry(&entry, &table, WIRING_LIMIT).unwrap();
+        assert_eq!(analysis.wirings.len(), 2 * 120);
+        assert!(analysis.complete);
+        assert_eq!(analysis.value(), Some(vec![1, 1]));
+        assert!(analysis.ambiguous().is_empty());
+        assert!(solve_entry(&entry, &table, 240).unwrap().complete);
+        let analysis = solve_entry(&entry, &table, 100).unwrap();
+        assert_eq!(analysis.wirings.len(), 100);
+        assert!(!analysis.complete);
+
+        // Five segments are a 2, 3 or 5. With both segments of the 1 lit it
+        // is a 3, with one of them a 2 or 5.
+        let entry: Entry = "cf | acdeg acdfg".parse().unwrap();
+        let analysis = solve_entry(&entry, &table, WIRING_LIMIT).unwrap();
+        assert_eq!(analysis.value(), None);
+        assert_eq!(analysis.ambiguous(), vec![0]);
+        assert_eq!(analysis.display[0], BTreeSet::from([2, 5]));
+        assert_eq!(analysis.display[1], BTreeSet::from([3]));
+    }
+
+    #[test]
+    fn test_no_wiring() {
+        let table = GlyphTable::digits();
+
+        // Two different ones.
+        let entry: Entry = "abcdefg ab | cd ab".parse().unwrap();
+        let conflicting = vec!["cd".to_string(), "ab".to_string()];
+        assert_eq!(solve_entry(&entry, &table, WIRING_LIMIT).unwrap_err(), WiringError::NoSolution { conflicting });
+
+        // Nothing has six segments without g.
+        let entry: Entry = "acdfg abcdef | abc".parse().unwrap();
+        assert!(solve_entry(&entry, &table, WIRING_LIMIT).is_ok());
+        let entry: Entry = "abcdef | abcdeg abcdfg abcefg".parse().unwrap();
+        let conflicting = vec!["abcdef".to_string(), "abcdeg".to_string(), "abcdfg".to_string(), "abcefg".to_string()];
+        assert_eq!(solve_entry(&entry, &table, WIRING_LIMIT).unwrap_err(), WiringError::NoSolution { conflicting });
+    }
+
+    #[test]
+    fn test_bitmasks() {
+        for filename in ["test_inputs/08_01.txt", "test_inputs/08_02.txt", "inputs/08.txt"] {
+            let input = read_input(filename).unwrap();
+            assert_eq!(part2_fast(&input), Some(part2(&input) as u64));
+        }
+
+        // Segments beyond g do not fit into the bitmasks.
+        assert_eq!("abcdefgh".parse::<Pattern>().unwrap().mask(), None);
+        assert_eq!("ip".parse::<Pattern>().unwrap().mask(), None);
+        assert_eq!("ag".parse::<Pattern>().unwrap().mask(), Some(0b1000001));
+        let input = read_input("test_inputs/08_04.txt").unwrap();
+        assert!(input[0].masks().is_none());
+        assert_eq!(part2_fast(&input), None);
+
+        let digits = GlyphTable::digits();
+        let table = BitTable::new(&digits).unwrap();
+        assert_eq!(table.glyphs[1], 0b0100100);
+        assert_eq!(table.symbols[0b1111111], Some(8));
+        assert!(BitTable::new(&format!("{}\n. h", SEVEN_SEGMENT_DIGITS).parse().unwrap()).is_none());
+
+        // Entries missing a pattern may still have a unique wiring, which the
+        // rules do not always find.
+        let input = read_input("inputs/08.txt").unwrap();
+        for entry in &input {
+            for skip in 0..10 {
+                let patterns: Vec<String> = (0..10).filter(|&i| i != skip).map(|i| format!("{:?}", entry.patterns[i])).collect();
+                let line = format!("{} | {}", patterns.join(" "), Entry::format_patterns(&entry.display));
+                let partial: Entry = line.parse().unwrap();
+                let (patterns, display) = partial.masks().unwrap();
+                let fast = decode_entry_fast(&patterns, &display, &table);
+                let analysis = solve_entry(&partial, &digits, WIRING_LIMIT).unwrap();
+                let unique = if analysis.wirings.len() == 1 { analysis.value() } else { None };
+                assert_eq!(fast, unique);
+                if let Some(value) = decode_entry(&partial, &digits) {
+                    assert_eq!(fast, Some(value));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let table = GlyphTable::digits();
+        let input = read_input("test_inputs/08_01.txt").unwrap();
+        let explanation = explain_entry(&input[0], &table);
+        let lines: Vec<&str> = explanation.lines().collect();
+
+        assert_eq!(lines[1], "1. pattern `acedgfb` has 7 segments, so it is 8, so {a,b,c,d,e,f,g} map to {a,b,c,d,e,f,g}");
+        assert!(lines.contains(&"4. pattern `ab` has 2 segments, so it is 1, so {a,b} map to {c,f} and {c,d,e,f,g} to {a,b,d,e,g}"));
+        assert!(lines.contains(&"5. pattern `cdfbe` can only be 5, so {b,c,d,e,f} map to {a,b,d,f,g} and {a,g} to {c,e}"));
+        assert!(lines.contains(&"     d -> a"));
+        assert_eq!(lines[lines.len() - 2], "Final mapping: a->c b->f c->g d->a e->b f->d g->e");
+        assert_eq!(lines[lines.len() - 1], "Output value: 5353");
+
+        let entry: Entry = "ab cd | ab".parse().unwrap();
+        let explanation = explain_entry(&entry, &table);
+        assert!(explanation.contains("pattern `cd` has 2 segments, so it is 1, so {c,d} map to {c,f}"));
+        assert!(explanation.ends_with("Rejected: the wiring cannot be resolved.\n"));
+    }
+
+    #[test]
+    fn test_encoder() {
+        let table = GlyphTable::digits();
+        let entries = generate_entries(500, &table, None, 49).unwrap();
+        let lines: Vec<String> = entries.iter().map(|(entry, _)| format!("{:?}\n", entry)).collect();
+        let filename = std::env::temp_dir().join("adventofcode2021_08_encoder.txt");
+        std::fs::write(&filename, lines.concat()).unwrap();
+
+        let input = read_input(filename.to_str().unwrap()).unwrap();
+        std::fs::remove_file(&filename).unwrap();
+        let bits = BitTable::new(&table).unwrap();
+        for ((entry, value), read) in entries.iter().zip(input.iter()) {
+            assert_eq!(format!("{:?}", read), format!("{:?}", entry));
+            assert_eq!(decode_entry(read, &table).as_ref(), Some(value));
+            let (patterns, display) = read.masks().unwrap();
+            assert_eq!(decode_entry_fast(&patterns, &display, &bits).as_ref(), Some(value));
+        }
+
+        // The wiring of the example in the puzzle.
+        let wiring: Wiring = "cfgabde".parse().unwrap();
+        let (entry, value) = &generate_entries(1, &table, Some(&wiring), 1).unwrap()[0];
+        let analysis = solve_entry(entry, &table, WIRING_LIMIT).unwrap();
+        assert_eq!(analysis.wirings, vec![wiring]);
+        assert_eq!(analysis.value().as_ref(), Some(value));
+
+        let hex = GlyphTable::hex();
+        for (entry, value) in generate_entries(20, &hex, None, 16).unwrap() {
+            assert_eq!(entry.patterns.len(), 16);
+            assert_eq!(decode_entry(&entry, &hex), Some(value));
+        }
+
+        assert!(matches!("abcdefa".parse::<Wiring>(), Err(SegmentError::DuplicateName('a'))));
+        assert!(matches!("abcdegh".parse::<Wiring>(), Err(SegmentError::MissingName('f'))));
+        assert!(matches!("abcdegi".parse::<Wiring>(), Err(SegmentError::MissingName('f'))));
+        assert_eq!(Wiring::parse("abc", &table).unwrap_err(), SegmentError::WrongCount { expected: 7, actual: 3 });
+        let short: Wiring = "abc".parse().unwrap();
+        assert_eq!(
+            encode_entry(&[1], &short, &table, &mut Rng::new(1)).unwrap_err(),
+            SegmentError::WrongCount { expected: 7, actual: 3 }
+        );
+        assert!(generate_entries(1, &table, Some(&short), 1).is_err());
+    }
+
+    #[test]
+    fn test_candidate_rules() {
+        let table = GlyphTable::digits();
+        let pattern: Pattern = "abcde".parse().unwrap();
+        let segments = |names: &str| -> HashSet<Segment> { names.bytes().map(|c| Segment(c - b'a')).collect() };
+
+        let mut rules = Rules::full(&table);
+        let numbers = match rules.test_pattern(&pattern) {
+            Rule::Many(numbers) => numbers,
+            _ => panic!("expected several numbers"),
+        };
+        assert_eq!(numbers, vec![2, 3, 5]);
+
+        // Lit code segments may light anything one of 2, 3 and 5 lights, the
+        // dark ones anything one of them leaves dark.
+        assert!(rules.update_with_candidates(&pattern, &numbers));
+        assert_eq!(rules.rules[&Segment(0)], segments("abcdefg"));
+        assert_eq!(rules.rules[&Segment(5)], segments("bcef"));
+        assert_eq!(rules.rules[&Segment(6)], segments("bcef"));
+        assert!(!rules.update_with_candidates(&pattern, &numbers));
+
+        // Requiring every number at once leaves three clear segments for the
+        // five lit code segments.
+        let mut strict = Rules::full(&table);
+        for &number in &numbers {
+            strict.update_with_known_pattern(&pattern, number);
+        }
+        assert_eq!(strict.rules[&Segment(0)], segments("adg"));
+    }
+
+    #[test]
+    fn test_segment_matching() {
+        // Taking the last choice that contains a segment, as a greedy store
+        // would, hands {a,b} to a and leaves nothing for b.
+        let (a, b) = (Segment(0), Segment(1));
+        let only_a: HashSet<Segment> = HashSet::from([a]);
+        let a_or_b: HashSet<Segment> = HashSet::from([a, b]);
+        let mut store = SegmentStore::new();
+        store.add_clears(&only_a);
+        store.add_clears(&a_or_b);
+        assert!(store.aquire_all(&[a, b]));
+        assert!(store.aquire_all(&[b, a]));
+        assert!(!store.aquire_all(&[b, b]));
+        assert!(!store.aquire_all(&[a]));
+
+        // A hex entry a greedy store fails to resolve.
+        let parse = |patterns: &str| patterns.split(' ').map(|p| p.parse().unwrap()).collect();
+        let entry = Entry {
+            patterns: parse("cgbfa fbgead agcdb bdga abdce fdegac agedbc adfe egcda cbgd dafbgec gaefc fe aefcb fgdecb gfe"),
+            display: parse("fgbaedc gcfaed ef faecb"),
+        };
+        assert_eq!(decode_entry(&entry, &GlyphTable::hex()), Some(vec![8, 9, 1, 13]));
+    }
+
+    #[test]
+    fn test_faults() {
+        let table = BitTable::new(&GlyphTable::digits()).unwrap();
+        let entry = &read_input("test_inputs/08_01.txt").unwrap()[0];
+        let (patterns, display) = entry.masks().unwrap();
+
+        let healthy = decode_with_faults(&patterns, &display, &table, 2).unwrap();
+        assert_eq!(healthy.faults.count(), 0);
+        assert_eq!(healthy.value, vec![5, 3, 5, 3]);
+        assert_eq!(healthy.explanations, 1);
+        assert_eq!(healthy.confidence, 1.0);
+
+        // The dead segment may hide the difference between 5 and 9, which
+        // leaves four equally likely readings.
+        for wire in 0..7 {
+            for faults in [Faults { stuck_on: 1 << wire, stuck_off: 0 }, Faults { stuck_on: 0, stuck_off: 1 << wire }] {
+                let broken = |masks: &[u8]| -> Vec<u8> {
+                    masks.iter().map(|mask| (mask | faults.stuck_on) & !faults.stuck_off).collect()
+                };
+                let (patterns, display) = (broken(&patterns), broken(&display));
+                // Without faults the entry is rejected or, worse, misread.
+                assert_ne!(decode_entry_fast(&patterns, &display, &table), Some(vec![5, 3, 5, 3]));
+                assert!(decode_with_faults(&patterns, &display, &table, 0).is_none());
+
+                let decoding = decode_with_faults(&patterns, &display, &table, 1).unwrap();
+                assert_eq!(decoding.faults, faults);
+                assert_eq!(decoding.wiring, healthy.wiring);
+                assert_eq!(decoding.value, vec![5, 3, 5, 3]);
+                let expected = if [0, 4, 6].contains(&wire) { 0.25 } else { 1.0 };
+                assert_eq!(decoding.confidence, expected);
+            }
+        }
+
+        // Hex displays have more symbols to tell apart.
+        let hex = BitTable::new(&GlyphTable::hex()).unwrap();
+        let entry = &read_input("test_inputs/08_03.txt").unwrap()[0];
+        let (patterns, display) = entry.masks().unwrap();
+        let healthy = decode_with_faults(&patterns, &display, &hex, 1).unwrap();
+        assert_eq!(healthy.faults.count(), 0);
+        let stuck_on = |masks: &[u8]| -> Vec<u8> { masks.iter().map(|mask| mask | 1).collect() };
+        let decoding = decode_with_faults(&stuck_on(&patterns), &stuck_on(&display), &hex, 1).unwrap();
+        assert_eq!(decoding.faults, Faults { stuck_on: 1, stuck_off: 0 });
+        assert_eq!(decoding.wiring, healthy.wiring);
+    }
 }
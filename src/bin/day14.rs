@@ -1,3 +1,4 @@
+use adventofcode2021::biguint::{BigUint, Count};
 use std::collections::HashMap;
 use std::fs::File;
 use std::io::{BufRead, BufReader};
@@ -38,22 +39,27 @@ fn read_input(filename: &str) -> (Polymer, Rules) {
 
 type CacheKey = (char, char, u16);
 
-fn add_counts(dest: &mut HashMap<char, u64>, src: &HashMap<char, u64>) {
+fn add_counts<T: Count>(dest: &mut HashMap<char, T>, src: &HashMap<char, T>) {
     for (c, count) in src.iter() {
         if let Some(old_count) = dest.get_mut(c) {
-            *old_count += count;
+            *old_count = old_count.clone() + count.clone();
         } else {
-            dest.insert(*c, *count);
+            dest.insert(*c, count.clone());
         }
     }
 }
 
-fn polymerize_internal(
+fn increment<T: Count>(counts: &mut HashMap<char, T>, c: char) {
+    let count = counts.get(&c).cloned().unwrap_or_else(|| T::from(0));
+    counts.insert(c, count + T::from(1));
+}
+
+fn polymerize_internal<T: Count>(
     left: char,
     right: char,
     rules: &Rules,
     steps: u16,
-    cache: &mut HashMap<CacheKey, HashMap<char, u64>>,
+    cache: &mut HashMap<CacheKey, HashMap<char, T>>,
 ) -> CacheKey {
     let key = (left, right, steps);
     let mut counts = HashMap::new();
@@ -66,8 +72,7 @@ fn polymerize_internal(
     } else if steps > 0 {
         // Get component that needs to be placed in the middle.
         if let Some(&middle) = rules.get(&(left, right)) {
-            let count = *counts.get(&middle).unwrap_or(&0);
-            counts.insert(middle, count + 1);
+            increment(&mut counts, middle);
 
             // Recursively calculate which components must be placed between
             // the left (middle) component and the middle (right) one.
@@ -89,14 +94,15 @@ fn polymerize_internal(
     key
 }
 
-fn polymerize(polymer: &Polymer, rules: &Rules, steps: u16) -> HashMap<char, u64> {
+/// Count the components after `steps` steps. Counting in `BigUint` keeps the
+/// counts exact for any number of steps.
+fn polymerize<T: Count>(polymer: &Polymer, rules: &Rules, steps: u16) -> HashMap<char, T> {
     let mut counts = HashMap::new();
     let mut cache = HashMap::new();
 
     // For every two adjacent components, calculate what must be placed in between them.
     for (&left, &right) in polymer.iter().zip(polymer.iter().skip(1)) {
-        let count = *counts.get(&left).unwrap_or(&0);
-        counts.insert(left, count + 1);
+        increment(&mut counts, left);
 
         let key = polymerize_internal(left, right, rules, steps, &mut cache);
         let new_counts = cache.get(&key).unwrap();
@@ -105,19 +111,18 @@ fn polymerize(polymer: &Polymer, rules: &Rules, steps: u16) -> HashMap<char, u64
 
     // Add last component of original polymer.
     let last = polymer.get(polymer.len() - 1).unwrap();
-    let count = *counts.get(last).unwrap_or(&0);
-    counts.insert(*last, count + 1);
+    increment(&mut counts, *last);
 
     counts
 }
 
-fn get_min_max_difference(polymer: &Polymer, rules: &Rules, steps: u16) -> u64 {
-    let counts = polymerize(&polymer, &rules, steps);
+fn get_min_max_difference<T: Count>(polymer: &Polymer, rules: &Rules, steps: u16) -> T {
+    let counts: HashMap<char, T> = polymerize(&polymer, &rules, steps);
 
     // println!("\nFinal:{:?}", counts);
 
-    let min = *counts.values().min().unwrap();
-    let max = *counts.values().max().unwrap();
+    let min = counts.values().min().unwrap().clone();
+    let max = counts.values().max().unwrap().clone();
 
     max - min
 }
@@ -137,6 +142,13 @@ fn main() {
 
     println!("Part 1: {}", part1(&polymer, &rules));
     println!("Part 2: {}", part2(&polymer, &rules));
+
+    // Pass a number of steps to get the exact difference for any number of steps.
+    if let Some(steps) = std::env::args().nth(1) {
+        let steps = steps.parse().expect("Cannot parse number of steps");
+        let difference: BigUint = get_min_max_difference(&polymer, &rules, steps);
+        println!("After {} steps: {}", steps, difference);
+    }
 }
 
 #[cfg(test)]
@@ -154,4 +166,15 @@ mod tests14 {
         let (polymer, rules) = read_input("test_inputs/14.txt");
         assert_eq!(part2(&polymer, &rules), 2188189693529);
     }
+
+    #[test]
+    fn test03() {
+        let (polymer, rules) = read_input("test_inputs/14.txt");
+        let difference: BigUint = get_min_max_difference(&polymer, &rules, 40);
+        assert_eq!(difference.to_string(), "2188189693529");
+
+        // u64 would overflow long before this.
+        let difference: BigUint = get_min_max_difference(&polymer, &rules, 100);
+        assert_eq!(difference.to_string(), "2535296262066596202993060773164");
+    }
 }
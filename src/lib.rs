@@ -0,0 +1,4 @@
+//! Code shared by several days.
+
+pub mod biguint;
+pub mod rng;
@@ -0,0 +1,86 @@
+//! Small SplitMix64 generator, so that simulations are reproducible from a seed.
+
+pub struct Rng {
+    state: u64,
+}
+
+impl Rng {
+    pub fn new(seed: u64) -> Rng {
+        Rng { state: seed }
+    }
+
+    pub fn next_u64(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9e3779b97f4a7c15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+        z ^ (z >> 31)
+    }
+
+    /// Uniformly distributed value in `0..n`.
+    pub fn below(&mut self, n: usize) -> usize {
+        ((self.next_u64() as u128 * n as u128) >> 64) as usize
+    }
+
+    pub fn shuffle<T>(&mut self, values: &mut [T]) {
+        for i in (1..values.len()).rev() {
+            values.swap(i, self.below(i + 1));
+        }
+    }
+
+    /// Uniformly distributed value in `[0, 1)`.
+    pub fn next_f64(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// `true` with probability `p`.
+    pub fn chance(&mut self, p: f64) -> bool {
+        self.next_f64() < p
+    }
+
+    /// Standard normally distributed value (Box-Muller transform).
+    pub fn normal(&mut self) -> f64 {
+        let u = 1.0 - self.next_f64();
+        let v = self.next_f64();
+        (-2.0 * u.ln()).sqrt() * (2.0 * std::f64::consts::PI * v).cos()
+    }
+
+    /// Number of successes in `n` trials with probability `p` each. Exact up
+    /// to a thousand trials, beyond that a rounded normal approximation.
+    pub fn binomial(&mut self, n: u64, p: f64) -> u64 {
+        if p >= 1.0 {
+            return n;
+        }
+        if p <= 0.0 || n == 0 {
+            return 0;
+        }
+        if n <= 1000 {
+            return (0..n).filter(|_| self.chance(p)).count() as u64;
+        }
+
+        let mean = n as f64 * p;
+        let deviation = (mean * (1.0 - p)).sqrt();
+        (mean + deviation * self.normal()).round().clamp(0.0, n as f64) as u64
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test01() {
+        let mut rng = Rng::new(42);
+        assert_eq!(rng.binomial(1_000_000, 1.0), 1_000_000);
+        assert_eq!(rng.binomial(1_000_000, 0.0), 0);
+
+        let small: u64 = (0..100).map(|_| rng.binomial(100, 0.3)).sum();
+        assert!((2700..3300).contains(&small));
+        let large = rng.binomial(1_000_000, 0.3);
+        assert!((297_000..303_000).contains(&large));
+
+        let mut a = Rng::new(7);
+        let mut b = Rng::new(7);
+        assert!((0..10).all(|_| a.next_u64() == b.next_u64()));
+    }
+}